@@ -0,0 +1,454 @@
+//! Encoding of the calls made to the pool factories on remote chains.
+//!
+//! Each registered chain names the `Encoding` its factory understands, which selects the
+//! `PayloadEncoder` building its payloads and validating its tokens and accounts.
+
+use crate::state::{ChainInfo, Encoding};
+use crate::ContractError;
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_vec, Timestamp, Uint256};
+use sha3::{Digest, Keccak256};
+
+/// Selector of `create_pool(address,uint256,uint256,uint256)`.
+const CREATE_POOL_SELECTOR: [u8; 4] = [0x3d, 0x72, 0xdc, 0xa8];
+/// Selector of `swap(uint256,uint256,address,uint256,uint256)`.
+const SWAP_SELECTOR: [u8; 4] = [0x89, 0xd7, 0x9c, 0x4c];
+/// Selector of `refund(uint256,uint256,address,uint256,uint256)`.
+const REFUND_SELECTOR: [u8; 4] = [0x39, 0x79, 0x2c, 0x94];
+/// Selector of `remove_liquidity(uint256,uint256,address,uint256,uint256)`.
+const REMOVE_LIQUIDITY_SELECTOR: [u8; 4] = [0xa8, 0x49, 0x05, 0x5a];
+/// Selector of `batch_transfer(uint256,address[],uint256[],uint256,uint256)`.
+const BATCH_TRANSFER_SELECTOR: [u8; 4] = [0x2f, 0xf6, 0x72, 0xb5];
+
+const BECH32_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+type Word = [u8; 32];
+
+/// Fields ending every factory call, letting the factory reject stale or replayed payloads.
+pub struct Envelope {
+    /// Nonce of the payload among those sent to the factory.
+    pub nonce: u64,
+    /// Time after which the payload must not be executed.
+    pub deadline: Timestamp,
+}
+
+/// A factory function paying out of a pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transfer {
+    /// Pay out a swap.
+    Swap,
+    /// Return a deposit.
+    Refund,
+    /// Pay out a withdrawal.
+    RemoveLiquidity,
+}
+
+/// Builds factory payloads for one kind of chain.
+pub trait PayloadEncoder {
+    /// Validate a token which can be listed on the chain, returning its canonical form.
+    fn canonical_token(&self, token: &str) -> Result<String, ContractError>;
+
+    /// Validate an account which can be paid on the chain, returning its canonical form.
+    fn canonical_address(&self, address: &str) -> Result<String, ContractError>;
+
+    /// Encode the creation of a pool trading `token`.
+    fn create_pool(
+        &self,
+        token: &str,
+        pool_id: Uint256,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError>;
+
+    /// Encode a payment of `amount` out of a pool to `recipient`.
+    fn transfer(
+        &self,
+        transfer: Transfer,
+        pool_id: Uint256,
+        amount: Uint256,
+        recipient: &str,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError>;
+
+    /// Encode payments out of a pool to several recipients at once.
+    fn batch_transfer(
+        &self,
+        pool_id: Uint256,
+        transfers: &[(String, Uint256)],
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError>;
+}
+
+/// The encoder for a registered chain.
+pub fn encoder(chain_info: &ChainInfo) -> &'static dyn PayloadEncoder {
+    match (&chain_info.encoding, chain_info.require_checksum) {
+        (Encoding::Evm, false) => &Evm {
+            require_checksum: false,
+        },
+        (Encoding::Evm, true) => &Evm {
+            require_checksum: true,
+        },
+        (Encoding::CosmWasm, _) => &CosmWasm,
+    }
+}
+
+/// Solidity ABI calls with 20-byte hex tokens and accounts.
+///
+/// Every argument is a static 32-byte word, so calls are encoded directly from precomputed
+/// selectors.
+pub struct Evm {
+    /// Reject addresses which are not EIP-55 checksummed. Mixed-case addresses are always
+    /// checked.
+    pub require_checksum: bool,
+}
+
+impl Evm {
+    /// Check the hex address is nonzero and, if mixed-case or required, EIP-55 checksummed.
+    /// Canonical addresses are lowercase with a `0x` prefix.
+    fn canonical(&self, address: &str) -> Result<String, ContractError> {
+        evm_address(address)?;
+        let hex = address.strip_prefix("0x").unwrap_or(address);
+        if hex.bytes().all(|b| b == b'0') {
+            return Err(ContractError::ZeroAddress {});
+        }
+        let lowercase = hex.to_ascii_lowercase();
+        let mixed_case = hex != lowercase && hex != hex.to_ascii_uppercase();
+        if (self.require_checksum || mixed_case) && hex != eip55(&lowercase) {
+            return Err(ContractError::InvalidChecksum {
+                address: address.to_string(),
+            });
+        }
+        Ok(format!("0x{lowercase}"))
+    }
+}
+
+impl PayloadEncoder for Evm {
+    fn canonical_token(&self, token: &str) -> Result<String, ContractError> {
+        self.canonical(token).map_err(|err| match err {
+            ContractError::InvalidAddress { .. } => ContractError::InvalidToken {
+                token: token.to_string(),
+            },
+            err => err,
+        })
+    }
+
+    fn canonical_address(&self, address: &str) -> Result<String, ContractError> {
+        self.canonical(address)
+    }
+
+    /// Encode `create_pool(address token, uint256 pool_id, uint256 nonce, uint256 deadline)`.
+    fn create_pool(
+        &self,
+        token: &str,
+        pool_id: Uint256,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        Ok(encode_call(
+            CREATE_POOL_SELECTOR,
+            [evm_address(token)?, pool_id.to_be_bytes()],
+            envelope,
+        ))
+    }
+
+    /// Encode `<transfer>(uint256 pool_id, uint256 amount, address recipient, uint256 nonce,
+    /// uint256 deadline)`.
+    fn transfer(
+        &self,
+        transfer: Transfer,
+        pool_id: Uint256,
+        amount: Uint256,
+        recipient: &str,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        let selector = match transfer {
+            Transfer::Swap => SWAP_SELECTOR,
+            Transfer::Refund => REFUND_SELECTOR,
+            Transfer::RemoveLiquidity => REMOVE_LIQUIDITY_SELECTOR,
+        };
+        Ok(encode_call(
+            selector,
+            [
+                pool_id.to_be_bytes(),
+                amount.to_be_bytes(),
+                evm_address(recipient)?,
+            ],
+            envelope,
+        ))
+    }
+
+    /// Encode `batch_transfer(uint256 pool_id, address[] recipients, uint256[] amounts,
+    /// uint256 nonce, uint256 deadline)`.
+    fn batch_transfer(
+        &self,
+        pool_id: Uint256,
+        transfers: &[(String, Uint256)],
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        let count = transfers.len() as u64;
+        let mut data = Vec::with_capacity(4 + 32 * (7 + 2 * transfers.len()));
+        data.extend_from_slice(&BATCH_TRANSFER_SELECTOR);
+        // Five head words, the arrays following as length-prefixed tails at their offsets.
+        for word in [
+            pool_id.to_be_bytes(),
+            Uint256::from(32 * 5u64).to_be_bytes(),
+            Uint256::from(32 * (6 + count)).to_be_bytes(),
+            Uint256::from(envelope.nonce).to_be_bytes(),
+            Uint256::from(envelope.deadline.seconds()).to_be_bytes(),
+        ] {
+            data.extend_from_slice(&word);
+        }
+        data.extend_from_slice(&Uint256::from(count).to_be_bytes());
+        for (recipient, _) in transfers {
+            data.extend_from_slice(&evm_address(recipient)?);
+        }
+        data.extend_from_slice(&Uint256::from(count).to_be_bytes());
+        for (_, amount) in transfers {
+            data.extend_from_slice(&amount.to_be_bytes());
+        }
+        Ok(data)
+    }
+}
+
+fn encode_call<const N: usize>(
+    selector: [u8; 4],
+    words: [Word; N],
+    envelope: &Envelope,
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity(4 + 32 * (N + 2));
+    data.extend_from_slice(&selector);
+    for word in words.iter().chain(&[
+        Uint256::from(envelope.nonce).to_be_bytes(),
+        Uint256::from(envelope.deadline.seconds()).to_be_bytes(),
+    ]) {
+        data.extend_from_slice(word);
+    }
+    data
+}
+
+/// Left-pad a hex address, with or without a `0x` prefix, to a word.
+fn evm_address(address: &str) -> Result<Word, ContractError> {
+    let invalid = || ContractError::InvalidAddress {
+        address: address.to_string(),
+    };
+    let hex = address.strip_prefix("0x").unwrap_or(address);
+    if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+    let mut word = [0u8; 32];
+    for (i, byte) in word[12..].iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(word)
+}
+
+/// Checksum a lowercase hex address without prefix, uppercasing each letter whose nibble in the
+/// Keccak-256 hash of the address is at least 8.
+fn eip55(lowercase: &str) -> String {
+    let hash = Keccak256::digest(lowercase.as_bytes());
+    lowercase
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0xf;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// JSON execute messages to a CosmWasm factory contract, with bech32 accounts and either native
+/// denoms or bech32 contract tokens.
+pub struct CosmWasm;
+
+/// Execute message of a CosmWasm factory contract.
+#[cw_serde]
+pub enum FactoryExecuteMsg {
+    /// Create a pool trading `token`.
+    CreatePool {
+        /// Token traded on the chain.
+        token: String,
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Nonce of the payload.
+        nonce: u64,
+        /// Time after which the payload must not be executed.
+        deadline: Timestamp,
+    },
+    /// Pay out a swap.
+    Swap(FactoryTransfer),
+    /// Return a deposit.
+    Refund(FactoryTransfer),
+    /// Pay out a withdrawal.
+    RemoveLiquidity(FactoryTransfer),
+    /// Make several payments out of a pool.
+    BatchTransfer {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Receiving accounts and the raw amounts paid to them.
+        transfers: Vec<FactoryPayment>,
+        /// Nonce of the payload.
+        nonce: u64,
+        /// Time after which the payload must not be executed.
+        deadline: Timestamp,
+    },
+}
+
+/// A payment in a batch made by a CosmWasm factory contract.
+#[cw_serde]
+pub struct FactoryPayment {
+    /// Receiving account.
+    pub recipient: String,
+    /// Raw amount to pay.
+    pub amount: Uint256,
+}
+
+/// A payment out of a pool by a CosmWasm factory contract.
+#[cw_serde]
+pub struct FactoryTransfer {
+    /// The pool ID.
+    pub pool_id: Uint256,
+    /// Raw amount to pay.
+    pub amount: Uint256,
+    /// Receiving account.
+    pub recipient: String,
+    /// Nonce of the payload.
+    pub nonce: u64,
+    /// Time after which the payload must not be executed.
+    pub deadline: Timestamp,
+}
+
+impl PayloadEncoder for CosmWasm {
+    fn canonical_token(&self, token: &str) -> Result<String, ContractError> {
+        let is_denom = (3..=128).contains(&token.len())
+            && token.starts_with(|c: char| c.is_ascii_alphabetic())
+            && token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "/:._-".contains(c));
+        if is_denom || is_bech32(token) {
+            Ok(token.to_string())
+        } else {
+            Err(ContractError::InvalidToken {
+                token: token.to_string(),
+            })
+        }
+    }
+
+    fn canonical_address(&self, address: &str) -> Result<String, ContractError> {
+        if is_bech32(address) {
+            Ok(address.to_string())
+        } else {
+            Err(ContractError::InvalidAddress {
+                address: address.to_string(),
+            })
+        }
+    }
+
+    fn create_pool(
+        &self,
+        token: &str,
+        pool_id: Uint256,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        self.canonical_token(token)?;
+        Ok(to_vec(&FactoryExecuteMsg::CreatePool {
+            token: token.to_string(),
+            pool_id,
+            nonce: envelope.nonce,
+            deadline: envelope.deadline,
+        })?)
+    }
+
+    fn transfer(
+        &self,
+        transfer: Transfer,
+        pool_id: Uint256,
+        amount: Uint256,
+        recipient: &str,
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        self.canonical_address(recipient)?;
+        let transfer_msg = FactoryTransfer {
+            pool_id,
+            amount,
+            recipient: recipient.to_string(),
+            nonce: envelope.nonce,
+            deadline: envelope.deadline,
+        };
+        Ok(to_vec(&match transfer {
+            Transfer::Swap => FactoryExecuteMsg::Swap(transfer_msg),
+            Transfer::Refund => FactoryExecuteMsg::Refund(transfer_msg),
+            Transfer::RemoveLiquidity => FactoryExecuteMsg::RemoveLiquidity(transfer_msg),
+        })?)
+    }
+
+    fn batch_transfer(
+        &self,
+        pool_id: Uint256,
+        transfers: &[(String, Uint256)],
+        envelope: &Envelope,
+    ) -> Result<Vec<u8>, ContractError> {
+        let transfers = transfers
+            .iter()
+            .map(|(recipient, amount)| {
+                self.canonical_address(recipient)?;
+                Ok(FactoryPayment {
+                    recipient: recipient.clone(),
+                    amount: *amount,
+                })
+            })
+            .collect::<Result<_, ContractError>>()?;
+        Ok(to_vec(&FactoryExecuteMsg::BatchTransfer {
+            pool_id,
+            transfers,
+            nonce: envelope.nonce,
+            deadline: envelope.deadline,
+        })?)
+    }
+}
+
+/// Whether `address` is a lowercase bech32 string with a valid checksum.
+fn is_bech32(address: &str) -> bool {
+    let (hrp, data) = match address.rsplit_once('1') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    if hrp.is_empty()
+        || data.len() < 6
+        || address.len() > 90
+        || !hrp
+            .bytes()
+            .all(|b| (33..=126).contains(&b) && !b.is_ascii_uppercase())
+    {
+        return false;
+    }
+    let values = match data
+        .bytes()
+        .map(|b| BECH32_CHARSET.iter().position(|&c| c == b).map(|v| v as u8))
+        .collect::<Option<Vec<u8>>>()
+    {
+        Some(values) => values,
+        None => return false,
+    };
+    let mut checksum_input: Vec<u8> = hrp.bytes().map(|b| b >> 5).collect();
+    checksum_input.push(0);
+    checksum_input.extend(hrp.bytes().map(|b| b & 31));
+    checksum_input.extend(values);
+    bech32_polymod(&checksum_input) == 1
+}
+
+fn bech32_polymod(values: &[u8]) -> u32 {
+    const GENERATORS: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    values.iter().fold(1, |chk, &value| {
+        let top = chk >> 25;
+        GENERATORS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(
+                ((chk & 0x1ffffff) << 5) ^ u32::from(value),
+                |chk, (_, g)| chk ^ g,
+            )
+    })
+}
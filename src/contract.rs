@@ -1,34 +1,65 @@
 //! Execute cross chain transactions.
 
-use crate::ContractError::PoolExists;
+use crate::ContractError::{
+    BatchNotClosed, BatchNotFound, BootstrapNotExpired, ChainExists, ChainInUse, ChainNotFound,
+    ChainNotInPool, DepositTooSmall, DuplicateDeposit, EmptyPool, ExactOutInBatch, Expired,
+    InsufficientCreationFee, InsufficientLiquidity, InsufficientLiquidityMinted, IntentNotExpired,
+    IntentNotFound, InvalidAddress, InvalidDecimals, InvalidMatchingPolicy, InvalidNonce,
+    InvalidToken, NoMatchingInProgress, OutboxEmpty, PoolBootstrapped, PoolExists,
+    QueuedDepositNotFound, SettlementNotFound, SigningUnsupported, SlippageExceeded,
+    TokenNotAllowed, TokenNotRegistered, Unauthorized, UnexpectedFunds, UnorderedChains,
+    ZeroAmount,
+};
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_binary, Addr, Binary, CosmosMsg, Deps, DepsMut, Env, Isqrt, MessageInfo, Response,
-    StdResult, Uint256, Uint512,
+    to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal256, Deps, DepsMut, Empty, Env,
+    Isqrt, MessageInfo, Order, Response, StdError, StdResult, Storage, Timestamp, Uint256, Uint512,
 };
-use ethabi::{Address, Contract, Function, Param, ParamType, StateMutability, Token, Uint};
+use cw_storage_plus::Bound;
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
-use std::str::FromStr;
 
+use crate::abi::{self, Envelope, PayloadEncoder, Transfer};
+use crate::eip712;
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, PalomaMsg, QueryMsg};
+use crate::msg::{
+    AllowedTokensResponse, ChainResponse, ChainsResponse, ExecuteMsg, InstantiateMsg,
+    LiquidityInvariantResponse, LiquidityQueueResponse, PalomaMsg, PendingSettlementsResponse,
+    QueryMsg, QueuedDeposit, QueuedSwapResponse, QuoteResponse, SignedAction, SwapQueueEntry,
+    SwapQueueResponse, TokenResponse, TokensResponse, ZapIn,
+};
 use crate::state::{
-    LiquidityQueueElement, PoolInfo, PoolMetaInfo, QueueID, DEADLINE, LIQUIDITY, LIQUIDITY_QUEUE,
-    LIQUIDITY_QUEUE_IDS, POOLS_COUNT, POOLS_INFO, POOL_FACTORIES, POOL_IDS,
+    liquidity_queue, pools_info, ChainInfo, DepositIntent, Encoding, LiquidityQueueElement,
+    LpOwner, MatchingPolicy, MatchingShare, PayoutKind, PoolInfo, PoolMetaInfo, QueuedSwap,
+    Settlement, SwapBatch, SwapMode, SwapOrder, TokenInfo, ADMIN, ALLOWED_TOKENS, CHAIN_NONCES,
+    DEADLINE, DEPOSIT_INTENTS, DEPOSIT_INTENTS_COUNT, LIQUIDITY, LIQUIDITY_QUEUE_SEQ,
+    LOCKED_LIQUIDITY_HOLDER, MATCHING, MATCHING_BACKLOG, MATCHING_SHARE, OUTBOX,
+    PENDING_SETTLEMENTS, POOLS_COUNT, POOL_CREATION_FEE, POOL_FACTORIES, POOL_IDS,
+    PROCESSED_DEPOSITS, RELAYER, SETTLEMENTS_COUNT, SIGNER_NONCES, SWAP_BATCHES, SWAP_QUEUE,
+    SWAP_QUEUE_SEQ, TOKENS,
 };
 
 const MIN_LIQUIDITY: u16 = 1000u16;
+const NORMALIZED_DECIMALS: u8 = 18;
+const MAX_DECIMALS: u8 = 36;
+const FEE_NUMERATOR: u16 = 3;
+const FEE_DENOMINATOR: u16 = 1000;
+const BPS_DENOMINATOR: u16 = 10000;
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+const MAX_MATCH_ITERATIONS: usize = 20;
+const MAX_BATCH_ORDERS: usize = 50;
+const PAYLOAD_TTL: u64 = 3600;
 
 /// Instantiate the contract. Initialize the pools.
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     _env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    ADMIN.save(deps.storage, &info.sender)?;
     POOLS_COUNT.save(deps.storage, &Uint256::zero())?;
     DEADLINE.save(deps.storage, &msg.deadline)?;
     Ok(Response::new())
@@ -37,7 +68,7 @@ pub fn instantiate(
 /// Execute the contract. See ExecuteMsg submessages for details.
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
@@ -50,16 +81,24 @@ pub fn execute(
             token1,
             chain0_init_depositor,
             chain1_init_depositor,
+            bootstrap_deadline,
+            matching_policy,
+            batch_window,
         } => create_pool(
             deps,
             env,
+            info,
             chain0_id,
             chain1_id,
             token0,
             token1,
             chain0_init_depositor,
             chain1_init_depositor,
+            bootstrap_deadline,
+            matching_policy.unwrap_or_default(),
+            batch_window,
         ),
+        ExecuteMsg::RefundBootstrap { pool_id } => refund_expired_bootstrap(deps, env, pool_id),
         ExecuteMsg::Swap {
             chain_from_id,
             chain_to_id,
@@ -68,15 +107,37 @@ pub fn execute(
             sender,
             receiver,
             amount,
-        } => swap(
-            chain_from_id,
-            chain_to_id,
-            token_from,
-            token_to,
-            sender,
-            receiver,
-            amount,
-        ),
+            mode,
+            queue_expiry,
+            tx_hash,
+            log_index,
+        } => {
+            assert_relayer(deps.as_ref(), &info)?;
+            record_deposit(deps.storage, chain_from_id, &tx_hash, log_index)?;
+            swap(
+                deps,
+                env,
+                chain_from_id,
+                chain_to_id,
+                token_from,
+                token_to,
+                sender,
+                receiver,
+                amount,
+                mode,
+                queue_expiry,
+            )
+        }
+        ExecuteMsg::ProcessSwapQueue { pool_id } => {
+            Ok(Response::new().add_messages(process_swap_queue(deps, &env, pool_id)?))
+        }
+        ExecuteMsg::SetRelayer { relayer } => set_relayer(deps, info, relayer),
+        ExecuteMsg::AckSettlement { nonce } => ack_settlement(deps, info, nonce),
+        ExecuteMsg::FailSettlement { nonce } => fail_settlement(deps, env, info, nonce),
+        ExecuteMsg::FlushOutbox { chain_id, pool_id } => {
+            execute_flush_outbox(deps, env, info, chain_id, pool_id)
+        }
+        ExecuteMsg::SettleBatch { pool_id } => settle_expired_batch(deps, env, pool_id),
         ExecuteMsg::AddLiquidity {
             pool_id,
             chain_id,
@@ -84,46 +145,412 @@ pub fn execute(
             amount,
             sender,
             receiver,
-        } => add_liquidity(deps, pool_id, chain_id, token, amount, sender, receiver),
+            zap,
+            intent_id,
+            tx_hash,
+            log_index,
+        } => {
+            assert_relayer(deps.as_ref(), &info)?;
+            record_deposit(deps.storage, chain_id, &tx_hash, log_index)?;
+            let receiver = lp_holder(deps.as_ref(), receiver)?;
+            let response = add_liquidity(
+                deps.branch(),
+                env.clone(),
+                pool_id,
+                chain_id,
+                token,
+                amount,
+                sender,
+                receiver,
+                zap,
+                intent_id,
+            )?;
+            Ok(response.add_messages(process_swap_queue(deps, &env, pool_id)?))
+        }
+        ExecuteMsg::ContinueMatching { pool_id } => continue_matching(deps, env, pool_id),
+        ExecuteMsg::CancelQueuedDeposits { pool_id } => {
+            cancel_queued_deposits(deps, env, info.sender, pool_id)
+        }
+        ExecuteMsg::RegisterDepositIntent {
+            pool_id,
+            amount0,
+            amount1,
+            sender0,
+            sender1,
+            tolerance_bps,
+            expiry,
+        } => register_deposit_intent(
+            deps,
+            env,
+            info,
+            pool_id,
+            amount0,
+            amount1,
+            sender0,
+            sender1,
+            tolerance_bps,
+            expiry,
+        ),
+        ExecuteMsg::RefundDepositIntent { intent_id } => {
+            refund_deposit_intent(deps, env, intent_id)
+        }
         ExecuteMsg::RemoveLiquidity {
-            chain0_id,
-            chain1_id,
-            token0,
-            token1,
+            pool_id,
+            amount,
             receiver0,
             receiver1,
-            amount,
+            min_amount0,
+            min_amount1,
+            deadline,
         } => remove_liquidity(
-            deps, info, chain0_id, chain1_id, token0, token1, receiver0, receiver1, amount,
+            deps,
+            env,
+            info.sender,
+            pool_id,
+            amount,
+            receiver0,
+            receiver1,
+            min_amount0,
+            min_amount1,
+            deadline,
+        ),
+        ExecuteMsg::SubmitSignedIntent {
+            chain_id,
+            action,
+            nonce,
+            deadline,
+            signature,
+        } => submit_signed_intent(deps, env, chain_id, action, nonce, deadline, signature),
+        ExecuteMsg::RelayedRemoveLiquidity {
+            chain_id,
+            sender,
+            tx_hash,
+            log_index,
+            pool_id,
+            amount,
+            receiver0,
+            receiver1,
+            min_amount0,
+            min_amount1,
+            deadline,
+        } => {
+            assert_relayer(deps.as_ref(), &info)?;
+            record_deposit(deps.storage, chain_id, &tx_hash, log_index)?;
+            let holder = lp_holder(
+                deps.as_ref(),
+                LpOwner::Remote {
+                    chain_id,
+                    address: sender,
+                },
+            )?;
+            remove_liquidity(
+                deps,
+                env,
+                holder,
+                pool_id,
+                amount,
+                receiver0,
+                receiver1,
+                min_amount0,
+                min_amount1,
+                deadline,
+            )
+        }
+        ExecuteMsg::RemoveLiquiditySingle {
+            pool_id,
+            amount,
+            chain_id,
+            receiver,
+            min_amount_out,
+            deadline,
+        } => remove_liquidity_single(
+            deps,
+            env,
+            info.sender,
+            pool_id,
+            amount,
+            chain_id,
+            receiver,
+            min_amount_out,
+            deadline,
+        ),
+        ExecuteMsg::RegisterChain {
+            chain_id,
+            factory,
+            encoding,
+            require_checksum,
+        } => register_chain(
+            deps,
+            info,
+            chain_id,
+            ChainInfo {
+                factory,
+                encoding: encoding.unwrap_or_default(),
+                require_checksum: require_checksum.unwrap_or_default(),
+                batch_size: None,
+            },
         ),
-        ExecuteMsg::RegisterChain { chain_id, factory } => register_chain(deps, chain_id, factory),
+        ExecuteMsg::UpdateChain { chain_id, factory } => {
+            update_chain(deps, info, chain_id, factory)
+        }
+        ExecuteMsg::SetBatchSize {
+            chain_id,
+            batch_size,
+        } => set_batch_size(deps, info, chain_id, batch_size),
+        ExecuteMsg::DeregisterChain { chain_id } => deregister_chain(deps, info, chain_id),
+        ExecuteMsg::RegisterToken {
+            chain_id,
+            token,
+            decimals,
+            symbol,
+        } => register_token(deps, info, chain_id, token, decimals, symbol),
+        ExecuteMsg::AllowToken { chain_id, token } => {
+            set_token_allowed(deps, info, chain_id, token, true)
+        }
+        ExecuteMsg::DisallowToken { chain_id, token } => {
+            set_token_allowed(deps, info, chain_id, token, false)
+        }
+        ExecuteMsg::SetPoolCreationFee { fee } => set_pool_creation_fee(deps, info, fee),
     }
 }
 
+fn assert_admin(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    if ADMIN.load(deps.storage)? != info.sender {
+        return Err(Unauthorized {});
+    }
+    Ok(())
+}
+
 fn register_chain(
     deps: DepsMut,
+    info: MessageInfo,
+    chain_id: Uint256,
+    chain_info: ChainInfo,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let binding = chain_id.to_be_bytes();
+    let chain_id_key = binding.as_slice();
+    if POOL_FACTORIES.has(deps.storage, chain_id_key) {
+        return Err(ChainExists { chain_id });
+    }
+    POOL_FACTORIES.save(deps.storage, chain_id_key, &chain_info)?;
+    Ok(Response::new())
+}
+
+fn update_chain(
+    deps: DepsMut,
+    info: MessageInfo,
     chain_id: Uint256,
     factory: String,
 ) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let binding = chain_id.to_be_bytes();
+    let chain_id_key = binding.as_slice();
+    let mut chain_info = POOL_FACTORIES
+        .may_load(deps.storage, chain_id_key)?
+        .ok_or(ChainNotFound { chain_id })?;
+    chain_info.factory = factory;
+    POOL_FACTORIES.save(deps.storage, chain_id_key, &chain_info)?;
+    Ok(Response::new())
+}
+
+fn set_batch_size(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: Uint256,
+    batch_size: Option<u32>,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let binding = chain_id.to_be_bytes();
+    let chain_id_key = binding.as_slice();
+    let mut chain_info = POOL_FACTORIES
+        .may_load(deps.storage, chain_id_key)?
+        .ok_or(ChainNotFound { chain_id })?;
+    chain_info.batch_size = batch_size;
+    POOL_FACTORIES.save(deps.storage, chain_id_key, &chain_info)?;
+    Ok(Response::new())
+}
+
+fn deregister_chain(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let binding = chain_id.to_be_bytes();
+    let chain_id_key = binding.as_slice();
+    if !POOL_FACTORIES.has(deps.storage, chain_id_key) {
+        return Err(ChainNotFound { chain_id });
+    }
+    let pools = pools_info();
+    for index in [&pools.idx.chain0, &pools.idx.chain1] {
+        if let Some(pool_info) = index
+            .prefix(chain_id_key.to_vec())
+            .range(deps.storage, None, None, Order::Ascending)
+            .next()
+        {
+            let (_, pool_info) = pool_info?;
+            return Err(ChainInUse {
+                chain_id,
+                pool_id: pool_info.pool_id,
+            });
+        }
+    }
+    POOL_FACTORIES.remove(deps.storage, chain_id_key);
+    Ok(Response::new())
+}
+
+fn register_token(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: Uint256,
+    token: String,
+    decimals: u8,
+    symbol: String,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
     let binding = chain_id.to_be_bytes();
     let chain_id_key = binding.as_slice();
-    assert!(!POOL_FACTORIES.has(deps.storage, chain_id_key));
-    POOL_FACTORIES.save(deps.storage, chain_id_key, &factory)?;
+    let token = chain_encoder(deps.storage, chain_id)?.canonical_token(&token)?;
+    if decimals > MAX_DECIMALS {
+        return Err(InvalidDecimals { decimals });
+    }
+    TOKENS.save(
+        deps.storage,
+        (chain_id_key, token.as_str()),
+        &TokenInfo { decimals, symbol },
+    )?;
+    Ok(Response::new())
+}
+
+fn set_token_allowed(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: Uint256,
+    token: String,
+    allowed: bool,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    let token = chain_encoder(deps.storage, chain_id)?.canonical_token(&token)?;
+    let binding = chain_id.to_be_bytes();
+    let key = (binding.as_slice(), token.as_str());
+    if allowed {
+        load_token(deps.as_ref(), chain_id, &token)?;
+        ALLOWED_TOKENS.save(deps.storage, key, &Empty {})?;
+    } else {
+        ALLOWED_TOKENS.remove(deps.storage, key);
+    }
+    Ok(Response::new())
+}
+
+fn set_pool_creation_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee: Option<Coin>,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    if fee.as_ref().map_or(false, |fee| fee.amount.is_zero()) {
+        return Err(ZeroAmount {});
+    }
+    POOL_CREATION_FEE.save(deps.storage, &fee)?;
     Ok(Response::new())
 }
 
+/// Check that a pool may be listed with the given tokens. Unlisted tokens are only accepted in
+/// permissionless mode, in which case the returned message forwards the creation fee to the admin.
+/// Funds other than exactly the fee due are rejected.
+fn assert_pool_creation_allowed(
+    deps: Deps,
+    info: &MessageInfo,
+    tokens: [(Uint256, &String); 2],
+) -> Result<Option<BankMsg>, ContractError> {
+    let mut unlisted = None;
+    for (chain_id, token) in tokens {
+        if !ALLOWED_TOKENS.has(deps.storage, (chain_id.to_be_bytes().as_slice(), token)) {
+            unlisted = Some(TokenNotAllowed {
+                chain_id,
+                token: token.clone(),
+            });
+            break;
+        }
+    }
+    let err = match unlisted {
+        Some(err) => err,
+        None => {
+            if !info.funds.is_empty() {
+                return Err(UnexpectedFunds {});
+            }
+            return Ok(None);
+        }
+    };
+    let fee = match POOL_CREATION_FEE.may_load(deps.storage)?.flatten() {
+        Some(fee) => fee,
+        None => return Err(err),
+    };
+    let paid = info
+        .funds
+        .iter()
+        .find(|coin| coin.denom == fee.denom)
+        .map(|coin| coin.amount)
+        .unwrap_or_default();
+    if paid < fee.amount {
+        return Err(InsufficientCreationFee { fee });
+    }
+    if info.funds.len() != 1 || paid != fee.amount {
+        return Err(UnexpectedFunds {});
+    }
+    Ok(Some(BankMsg::Send {
+        to_address: ADMIN.load(deps.storage)?.into_string(),
+        amount: vec![fee],
+    }))
+}
+
+fn load_token(deps: Deps, chain_id: Uint256, token: &str) -> Result<TokenInfo, ContractError> {
+    TOKENS
+        .may_load(deps.storage, (chain_id.to_be_bytes().as_slice(), token))?
+        .ok_or_else(|| TokenNotRegistered {
+            chain_id,
+            token: token.to_string(),
+        })
+}
+
 #[allow(clippy::too_many_arguments)]
 fn create_pool(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
+    info: MessageInfo,
     chain0_id: Uint256,
     chain1_id: Uint256,
     token0: String,
     token1: String,
     chain0_init_depositor: String,
     chain1_init_depositor: String,
+    bootstrap_deadline: Option<u64>,
+    matching_policy: MatchingPolicy,
+    batch_window: Option<u64>,
 ) -> Result<Response<PalomaMsg>, ContractError> {
-    assert!(chain0_id < chain1_id);
+    if chain0_id >= chain1_id {
+        return Err(UnorderedChains {
+            chain0_id,
+            chain1_id,
+        });
+    }
+    if matches!(matching_policy, MatchingPolicy::RoundRobin { cap } if cap.is_zero()) {
+        return Err(InvalidMatchingPolicy {});
+    }
+    let encoder0 = chain_encoder(deps.storage, chain0_id)?;
+    let encoder1 = chain_encoder(deps.storage, chain1_id)?;
+    let token0 = encoder0.canonical_token(&token0)?;
+    let token1 = encoder1.canonical_token(&token1)?;
+    let chain0_init_depositor = encoder0.canonical_address(&chain0_init_depositor)?;
+    let chain1_init_depositor = encoder1.canonical_address(&chain1_init_depositor)?;
+    let fee_msg = assert_pool_creation_allowed(
+        deps.as_ref(),
+        &info,
+        [(chain0_id, &token0), (chain1_id, &token1)],
+    )?;
+    let token0_info = load_token(deps.as_ref(), chain0_id, &token0)?;
+    let token1_info = load_token(deps.as_ref(), chain1_id, &token1)?;
 
     let pool_meta_info = PoolMetaInfo {
         chain0_id,
@@ -134,16 +561,13 @@ fn create_pool(
     let binding = to_binary(&pool_meta_info)?;
     let meta_info_key = binding.as_slice();
     let pool_id;
+    let mut refund_msgs = vec![];
     if POOL_IDS.has(deps.storage, meta_info_key) {
         let id = POOL_IDS.load(deps.storage, meta_info_key)?;
-        let pool_info = POOLS_INFO.load(deps.storage, id.to_be_bytes().as_slice())?;
-        if (pool_info.amount0.is_zero() || pool_info.amount1.is_zero())
-            && pool_info
-                .timestamp
-                .plus_seconds(DEADLINE.load(deps.storage)?)
-                < env.block.time
-        {
+        let mut pool_info = pools_info().load(deps.storage, id.to_be_bytes().as_slice())?;
+        if pool_info.total_liquidity.is_zero() && bootstrap_expired(&pool_info, &env) {
             pool_id = id;
+            refund_msgs = refund_bootstrap(deps.branch(), &env, &mut pool_info)?;
         } else {
             return Err(PoolExists {
                 chain0_id,
@@ -154,6 +578,7 @@ fn create_pool(
         }
     } else {
         pool_id = POOLS_COUNT.load(deps.storage)?;
+        POOLS_COUNT.save(deps.storage, &(pool_id + Uint256::one()))?;
     }
     let pool_info = PoolInfo {
         pool_id,
@@ -163,451 +588,2338 @@ fn create_pool(
         pending_amount0: Uint256::zero(),
         pending_amount1: Uint256::zero(),
         total_liquidity: Uint256::zero(),
+        decimals0: token0_info.decimals,
+        decimals1: token1_info.decimals,
         timestamp: env.block.time,
+        bootstrap_deadline: match bootstrap_deadline {
+            Some(bootstrap_deadline) => bootstrap_deadline,
+            None => DEADLINE.load(deps.storage)?,
+        },
         chain0_init_depositor,
         chain1_init_depositor,
+        matching_policy,
+        batch_window,
     };
 
     POOL_IDS.save(deps.storage, meta_info_key, &pool_id)?;
     let binding = pool_id.to_be_bytes();
     let pool_id_key = binding.as_slice();
-    POOLS_INFO.save(deps.storage, pool_id_key, &pool_info)?;
-    LIQUIDITY_QUEUE_IDS.save(
-        deps.storage,
-        pool_id_key,
-        &QueueID {
-            start: 0,
-            length: 0,
-        },
-    )?;
-    #[allow(deprecated)]
-    let contract = Contract {
-        constructor: None,
-        functions: BTreeMap::from_iter(vec![(
-            "create_pool".to_string(),
-            vec![Function {
-                name: "create_pool".to_string(),
-                inputs: vec![
-                    Param {
-                        name: "token".to_string(),
-                        kind: ParamType::Address,
-                        internal_type: None,
-                    },
-                    Param {
-                        name: "pool_id".to_string(),
-                        kind: ParamType::Uint(256),
-                        internal_type: None,
-                    },
-                ],
-                outputs: Vec::new(),
-                constant: None,
-                state_mutability: StateMutability::NonPayable,
-            }],
-        )]),
-        events: BTreeMap::new(),
-        errors: BTreeMap::new(),
-        receive: false,
-        fallback: false,
-    };
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    let mut messages = vec![];
+    for (chain_id, token) in [(chain0_id, &token0), (chain1_id, &token1)] {
+        messages.push(factory_message(
+            deps.storage,
+            &env,
+            chain_id,
+            |encoder, envelope| encoder.create_pool(token, pool_id, envelope),
+        )?);
+    }
 
     Ok(Response::new()
-        .add_message(CosmosMsg::Custom(PalomaMsg {
-            job_id: POOL_FACTORIES.load(deps.storage, chain0_id.to_be_bytes().as_slice())?,
-            payload: Binary(
-                contract
-                    .function("create_pool")
-                    .unwrap()
-                    .encode_input(&[
-                        Token::Address(Address::from_str(token0.as_str()).unwrap()),
-                        Token::Uint(Uint::from_str(pool_id.to_string().as_str()).unwrap()),
-                    ])
-                    .unwrap(),
-            ),
-        }))
-        .add_message(CosmosMsg::Custom(PalomaMsg {
-            job_id: POOL_FACTORIES.load(deps.storage, chain1_id.to_be_bytes().as_slice())?,
-            payload: Binary(
-                contract
-                    .function("create_pool")
-                    .unwrap()
-                    .encode_input(&[
-                        Token::Address(Address::from_str(token1.as_str()).unwrap()),
-                        Token::Uint(Uint::from_str(pool_id.to_string().as_str()).unwrap()),
-                    ])
-                    .unwrap(),
-            ),
-        })))
+        .add_messages(fee_msg)
+        .add_messages(refund_msgs)
+        .add_messages(messages))
 }
 
-fn swap(
-    _chain_from_id: Uint256,
-    _chain_to_id: Uint256,
-    _token_from: String,
-    _token_to: String,
-    _sender: String,
-    _receiver: String,
-    _amount: Uint256,
-) -> Result<Response<PalomaMsg>, ContractError> {
-    unimplemented!()
+/// Whether the bootstrap deadline of a pool has passed.
+fn bootstrap_expired(pool_info: &PoolInfo, env: &Env) -> bool {
+    pool_info
+        .timestamp
+        .plus_seconds(pool_info.bootstrap_deadline)
+        < env.block.time
 }
 
-fn add_liquidity(
+/// Drain the liquidity queue of a pool which never bootstrapped, returning the deposits to their
+/// senders.
+fn refund_bootstrap(
     deps: DepsMut,
+    env: &Env,
+    pool_info: &mut PoolInfo,
+) -> Result<Vec<CosmosMsg<PalomaMsg>>, ContractError> {
+    let binding = pool_info.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut messages = vec![];
+    for liquidity_queue in clear_queue(deps.storage, pool_id_key)? {
+        messages.extend(payout(
+            deps.storage,
+            env,
+            PayoutKind::Refund,
+            liquidity_queue.chain_id,
+            pool_info.pool_id,
+            liquidity_queue.amount,
+            &liquidity_queue.sender,
+        )?);
+    }
+    pool_info.pending_amount0 = Uint256::zero();
+    pool_info.pending_amount1 = Uint256::zero();
+    pools_info().save(deps.storage, pool_id_key, pool_info)?;
+    Ok(messages)
+}
+
+fn refund_expired_bootstrap(
+    mut deps: DepsMut,
+    env: Env,
     pool_id: Uint256,
-    chain_id: Uint256,
-    token: String,
-    amount: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let mut pool_info = pools_info().load(deps.storage, pool_id.to_be_bytes().as_slice())?;
+    if !pool_info.total_liquidity.is_zero() {
+        return Err(PoolBootstrapped { pool_id });
+    }
+    if !bootstrap_expired(&pool_info, &env) {
+        return Err(BootstrapNotExpired {
+            pool_id,
+            deadline: pool_info
+                .timestamp
+                .plus_seconds(pool_info.bootstrap_deadline),
+        });
+    }
+    let messages = refund_bootstrap(deps.branch(), &env, &mut pool_info)?;
+    Ok(Response::new().add_messages(messages))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn swap(
+    deps: DepsMut,
+    env: Env,
+    chain_from_id: Uint256,
+    chain_to_id: Uint256,
+    token_from: String,
+    token_to: String,
     sender: String,
-    receiver: Addr,
+    receiver: String,
+    amount: Uint256,
+    mode: SwapMode,
+    queue_expiry: Option<u64>,
 ) -> Result<Response<PalomaMsg>, ContractError> {
+    let sender = chain_encoder(deps.storage, chain_from_id)?.canonical_address(&sender)?;
+    let receiver = chain_encoder(deps.storage, chain_to_id)?.canonical_address(&receiver)?;
+    let pool_id = find_pool_id(
+        deps.as_ref(),
+        chain_from_id,
+        chain_to_id,
+        token_from,
+        token_to,
+    )?;
     let binding = pool_id.to_be_bytes();
     let pool_id_key = binding.as_slice();
-    let mut pool_info = POOLS_INFO.load(deps.storage, pool_id_key)?;
-    assert!(!amount.is_zero());
-    let is_chain0 = if pool_info.meta.chain0_id == chain_id && pool_info.meta.token0 == token {
-        true
-    } else {
-        assert!(pool_info.meta.chain1_id == chain_id && pool_info.meta.token1 == token);
-        false
-    };
-
-    if pool_info.total_liquidity.is_zero() {
-        if is_chain0 {
-            assert_eq!(sender, pool_info.chain0_init_depositor);
-        } else {
-            assert_eq!(sender, pool_info.chain1_init_depositor);
-        }
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    if pool_info.batch_window.is_some() {
+        let min_amount_out = match mode {
+            SwapMode::ExactIn { min_amount_out } => min_amount_out,
+            SwapMode::ExactOut { .. } => return Err(ExactOutInBatch { pool_id }),
+        };
+        return add_to_batch(
+            deps,
+            env,
+            pool_info,
+            SwapOrder {
+                chain_from_id,
+                amount,
+                min_amount_out,
+                sender,
+                receiver,
+            },
+        );
     }
-
-    let mut liquidity_queue_id = LIQUIDITY_QUEUE_IDS.load(deps.storage, pool_id_key)?;
-    if liquidity_queue_id.length > 0 {
-        let mut id = liquidity_queue_id.start;
-        let liquidity_queue =
-            LIQUIDITY_QUEUE.load(deps.storage, (pool_id_key, id.to_be_bytes().as_slice()))?;
-        if liquidity_queue.chain_id == chain_id {
-            LIQUIDITY_QUEUE.save(
+    let result = execute_swap(
+        deps.storage,
+        &env,
+        &mut pool_info,
+        chain_from_id,
+        amount,
+        &mode,
+        &sender,
+        &receiver,
+    );
+    if let (Err(InsufficientLiquidity { .. } | EmptyPool { .. }), Some(expiry)) =
+        (&result, queue_expiry)
+    {
+        let expiry = Timestamp::from_seconds(expiry);
+        if expiry > env.block.time {
+            let seq = SWAP_QUEUE_SEQ
+                .may_load(deps.storage, pool_id_key)?
+                .unwrap_or_default();
+            SWAP_QUEUE_SEQ.save(deps.storage, pool_id_key, &(seq + 1))?;
+            SWAP_QUEUE.save(
                 deps.storage,
-                (pool_id_key, (id + 1).to_be_bytes().as_slice()),
-                &LiquidityQueueElement {
-                    chain_id,
+                (pool_id_key, seq),
+                &QueuedSwap {
+                    chain_from_id,
                     amount,
+                    mode,
+                    sender,
                     receiver,
+                    expiry,
                 },
             )?;
-            liquidity_queue_id.length += 1;
-            LIQUIDITY_QUEUE_IDS.save(deps.storage, pool_id_key, &liquidity_queue_id)?;
-            if is_chain0 {
-                pool_info.pending_amount0 += amount;
-            } else {
-                pool_info.pending_amount1 += amount;
+            return Ok(Response::new()
+                .add_attribute("queued_swap", seq.to_string())
+                .set_data(to_binary(&seq)?));
+        }
+    }
+    let messages = result?;
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(Response::new().add_messages(messages))
+}
+
+/// Trade a relayed swap against the reserves of a pool, returning the payout and the refund of
+/// any unused input. The pool is unchanged on error.
+#[allow(clippy::too_many_arguments)]
+fn execute_swap(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_info: &mut PoolInfo,
+    chain_from_id: Uint256,
+    amount: Uint256,
+    mode: &SwapMode,
+    sender: &str,
+    receiver: &str,
+) -> Result<Vec<CosmosMsg<PalomaMsg>>, ContractError> {
+    let is_chain0 = pool_info.meta.chain0_id == chain_from_id;
+    let (amount_in, amount_out) = match *mode {
+        SwapMode::ExactIn { min_amount_out } => {
+            let amount_out = get_amount_out(pool_info, is_chain0, amount)?;
+            if amount_out < min_amount_out {
+                return Err(SlippageExceeded {
+                    amount: amount_out,
+                    limit: min_amount_out,
+                });
             }
-        } else if pool_info.total_liquidity.is_zero() {
-            let liquidity = if pool_info.pending_amount0.is_zero() {
-                pool_info.amount0 = amount;
-                pool_info.amount1 = pool_info.pending_amount1;
-                (pool_info.pending_amount1 * amount).isqrt()
-            } else {
-                pool_info.amount1 = amount;
-                pool_info.amount0 = pool_info.pending_amount0;
-                (pool_info.pending_amount0 * amount).isqrt()
-            };
-            if receiver.eq(&liquidity_queue.receiver) {
-                LIQUIDITY.save(
-                    deps.storage,
-                    (pool_id_key, receiver.as_bytes()),
-                    &(liquidity - Uint256::from(MIN_LIQUIDITY)),
-                )?;
-            } else {
-                LIQUIDITY.save(
-                    deps.storage,
-                    (pool_id_key, receiver.as_bytes()),
-                    &((liquidity - Uint256::from(MIN_LIQUIDITY)) / Uint256::from(2u8)),
-                )?;
-                LIQUIDITY.save(
-                    deps.storage,
-                    (pool_id_key, liquidity_queue.receiver.as_bytes()),
-                    &((liquidity - Uint256::from(MIN_LIQUIDITY)) / Uint256::from(2u8)),
-                )?;
+            (amount, amount_out)
+        }
+        SwapMode::ExactOut {
+            amount_out,
+            max_amount_in,
+        } => {
+            let amount_in = get_amount_in(pool_info, is_chain0, amount_out)?;
+            let limit = max_amount_in.min(amount);
+            if amount_in > limit {
+                return Err(SlippageExceeded {
+                    amount: amount_in,
+                    limit,
+                });
             }
-            pool_info.pending_amount0 = Uint256::zero();
-            pool_info.pending_amount1 = Uint256::zero();
-            pool_info.total_liquidity = liquidity;
-            LIQUIDITY_QUEUE_IDS.save(
-                deps.storage,
-                pool_id_key,
-                &QueueID {
-                    start: 0,
-                    length: 0,
-                },
-            )?;
-        } else {
-            let mut queue_amount = if is_chain0 {
-                amount * pool_info.amount1 / pool_info.amount0
-            } else {
-                amount * pool_info.amount0 / pool_info.amount1
-            };
-            let mut input_amount = amount;
-            let limit = liquidity_queue_id.start + liquidity_queue_id.length;
-            while id < limit && !input_amount.is_zero() {
-                let binding = id.to_be_bytes();
-                let id_key = binding.as_slice();
-                let mut liquidity_queue =
-                    LIQUIDITY_QUEUE.load(deps.storage, (pool_id_key, id_key))?;
-                let input_token = match liquidity_queue.amount.cmp(&queue_amount) {
-                    Ordering::Less => {
-                        queue_amount -= liquidity_queue.amount;
-                        id += 1;
-                        let new_amount = if is_chain0 {
-                            pool_info.pending_amount1 -= liquidity_queue.amount;
-                            Uint256::try_from(
-                                Uint512::from(liquidity_queue.amount)
-                                    * Uint512::from(pool_info.amount0)
-                                    / Uint512::from(pool_info.amount1),
-                            )
-                            .unwrap()
-                        } else {
-                            pool_info.pending_amount0 -= liquidity_queue.amount;
-                            Uint256::try_from(
-                                Uint512::from(liquidity_queue.amount)
-                                    * Uint512::from(pool_info.amount1)
-                                    / Uint512::from(pool_info.amount0),
-                            )
-                            .unwrap()
-                        };
-                        if input_amount > new_amount {
-                            input_amount -= new_amount;
-                        } else {
-                            queue_amount = Uint256::zero();
-                            input_amount = Uint256::zero();
-                        }
-                        liquidity_queue.amount
-                    }
-                    Ordering::Equal => {
-                        liquidity_queue_id.length -= id + 1 - liquidity_queue_id.start;
-                        liquidity_queue_id.start = id + 1;
-                        if is_chain0 {
-                            pool_info.pending_amount1 -= queue_amount;
-                        } else {
-                            pool_info.pending_amount0 -= queue_amount;
-                        };
-                        queue_amount = Uint256::zero();
-                        input_amount = Uint256::zero();
-                        queue_amount
-                    }
-                    Ordering::Greater => {
-                        liquidity_queue.amount -= queue_amount;
-                        liquidity_queue_id.length -= id - liquidity_queue_id.start;
-                        liquidity_queue_id.start = id;
-                        if is_chain0 {
-                            pool_info.pending_amount1 -= queue_amount;
-                        } else {
-                            pool_info.pending_amount0 -= queue_amount;
-                        };
-                        LIQUIDITY_QUEUE.save(
-                            deps.storage,
-                            (pool_id_key, id_key),
-                            &liquidity_queue,
-                        )?;
-                        queue_amount = Uint256::zero();
-                        input_amount = Uint256::zero();
-                        queue_amount
-                    }
-                };
-
-                let liq = if is_chain0 {
-                    Uint256::try_from(
-                        Uint512::from(input_token) * Uint512::from(pool_info.amount0)
-                            / Uint512::from(pool_info.amount1)
-                            * Uint512::from(input_token).isqrt(),
-                    )
-                    .unwrap()
-                } else {
-                    Uint256::try_from(
-                        Uint512::from(input_token) * Uint512::from(pool_info.amount1)
-                            / Uint512::from(pool_info.amount0)
-                            * Uint512::from(input_token).isqrt(),
-                    )
-                    .unwrap()
-                };
-                LIQUIDITY.update(
-                    deps.storage,
-                    (pool_id_key, receiver.as_bytes()),
-                    |liquidity| -> StdResult<_> {
-                        Ok(liquidity.unwrap_or_default() + liq / Uint256::from(2u8))
-                    },
-                )?;
-                LIQUIDITY.update(
-                    deps.storage,
-                    (pool_id_key, liquidity_queue.receiver.as_bytes()),
-                    |liquidity| -> StdResult<_> {
-                        Ok(liquidity.unwrap_or_default() + liq / Uint256::from(2u8))
-                    },
-                )?;
-                pool_info.total_liquidity += liq;
+            (amount_in, amount_out)
+        }
+    };
+    let chain_to_id = if is_chain0 {
+        pool_info.amount0 += amount_in;
+        pool_info.amount1 -= amount_out;
+        pool_info.meta.chain1_id
+    } else {
+        pool_info.amount1 += amount_in;
+        pool_info.amount0 -= amount_out;
+        pool_info.meta.chain0_id
+    };
+
+    let mut messages = Vec::from_iter(payout(
+        storage,
+        env,
+        PayoutKind::Swap {
+            chain_from_id,
+            amount_in,
+            sender: sender.to_string(),
+        },
+        chain_to_id,
+        pool_info.pool_id,
+        amount_out,
+        receiver,
+    )?);
+    if amount_in < amount {
+        messages.extend(payout(
+            storage,
+            env,
+            PayoutKind::Refund,
+            chain_from_id,
+            pool_info.pool_id,
+            amount - amount_in,
+            sender,
+        )?);
+    }
+    Ok(messages)
+}
+
+/// Fill the oldest `MAX_MATCH_ITERATIONS` queued swaps of a pool in order until one cannot be
+/// filled, refunding the expired ones.
+fn process_swap_queue(
+    deps: DepsMut,
+    env: &Env,
+    pool_id: Uint256,
+) -> Result<Vec<CosmosMsg<PalomaMsg>>, ContractError> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let queue = SWAP_QUEUE
+        .prefix(pool_id_key)
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(MAX_MATCH_ITERATIONS)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut messages = vec![];
+    let mut blocked = false;
+    for (seq, queued) in queue {
+        if queued.expiry <= env.block.time {
+            messages.extend(payout(
+                deps.storage,
+                env,
+                PayoutKind::Refund,
+                queued.chain_from_id,
+                pool_id,
+                queued.amount,
+                &queued.sender,
+            )?);
+        } else if blocked {
+            continue;
+        } else {
+            match execute_swap(
+                deps.storage,
+                env,
+                &mut pool_info,
+                queued.chain_from_id,
+                queued.amount,
+                &queued.mode,
+                &queued.sender,
+                &queued.receiver,
+            ) {
+                Ok(swap_messages) => messages.extend(swap_messages),
+                Err(_) => {
+                    blocked = true;
+                    continue;
+                }
             }
-            if !input_amount.is_zero() {
-                if is_chain0 {
-                    pool_info.pending_amount0 = input_amount;
-                    pool_info.pending_amount1 = Uint256::zero();
-                } else {
-                    pool_info.pending_amount0 = Uint256::zero();
-                    pool_info.pending_amount1 = input_amount;
+        }
+        SWAP_QUEUE.remove(deps.storage, (pool_id_key, seq));
+    }
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(messages)
+}
+
+/// Time after which the batch auction of a pool can be settled.
+fn batch_closes(pool_info: &PoolInfo, batch: &SwapBatch) -> Timestamp {
+    batch
+        .opened
+        .plus_seconds(pool_info.batch_window.unwrap_or_default())
+}
+
+/// Add a swap to the batch auction of a pool, settling the open batch first if its window has
+/// passed or it is full.
+fn add_to_batch(
+    mut deps: DepsMut,
+    env: Env,
+    mut pool_info: PoolInfo,
+    order: SwapOrder,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let binding = pool_info.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let (reserve0, reserve1) = normalized_reserves(&pool_info, true);
+    if reserve0.is_zero() || reserve1.is_zero() {
+        return Err(EmptyPool {
+            pool_id: pool_info.pool_id,
+        });
+    }
+    let mut messages = vec![];
+    let mut batch = SWAP_BATCHES.may_load(deps.storage, pool_id_key)?;
+    if let Some(open) = batch.take() {
+        if batch_closes(&pool_info, &open) <= env.block.time
+            || open.orders.len() >= MAX_BATCH_ORDERS
+        {
+            messages = settle_batch(deps.branch(), &env, &mut pool_info, open.orders)?;
+            pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+        } else {
+            batch = Some(open);
+        }
+    }
+    let mut batch = batch.unwrap_or(SwapBatch {
+        opened: env.block.time,
+        orders: vec![],
+    });
+    batch.orders.push(order);
+    SWAP_BATCHES.save(deps.storage, pool_id_key, &batch)?;
+    Ok(Response::new().add_messages(messages))
+}
+
+/// Outputs of a batch of swap orders cleared at one uniform price, or `None` for orders whose
+/// `min_amount_out` the price misses. Applying every filled input to the reserves at once, the
+/// price of token0 in token1 is `(reserve1 + in1) / (reserve0 + in0)` over fee-adjusted inputs,
+/// which keeps the constant product. The order missing its limit by the most is dropped and the
+/// price recomputed until it holds for all the rest.
+fn clear_batch(
+    pool_info: &PoolInfo,
+    orders: &[SwapOrder],
+) -> Result<Vec<Option<Uint256>>, ContractError> {
+    let (reserve0, reserve1) = normalized_reserves(pool_info, true);
+    if reserve0.is_zero() || reserve1.is_zero() {
+        return Err(EmptyPool {
+            pool_id: pool_info.pool_id,
+        });
+    }
+    let inputs: Vec<Uint512> = orders
+        .iter()
+        .map(|order| {
+            let decimals = if order.chain_from_id == pool_info.meta.chain0_id {
+                pool_info.decimals0
+            } else {
+                pool_info.decimals1
+            };
+            Uint512::from(normalize(order.amount, decimals))
+                * Uint512::from(FEE_DENOMINATOR - FEE_NUMERATOR)
+        })
+        .collect();
+    let mut filled = vec![true; orders.len()];
+    loop {
+        let mut side0 = Uint512::from(reserve0) * Uint512::from(FEE_DENOMINATOR);
+        let mut side1 = Uint512::from(reserve1) * Uint512::from(FEE_DENOMINATOR);
+        for ((order, input), _) in orders
+            .iter()
+            .zip(&inputs)
+            .zip(&filled)
+            .filter(|(_, filled)| **filled)
+        {
+            if order.chain_from_id == pool_info.meta.chain0_id {
+                side0 += *input;
+            } else {
+                side1 += *input;
+            }
+        }
+        let mut outputs = vec![];
+        // The missed order furthest from its limit, as `(index, amount_out)`.
+        let mut worst: Option<(usize, Uint256)> = None;
+        for (i, ((order, input), filled)) in orders.iter().zip(&inputs).zip(&filled).enumerate() {
+            if !*filled {
+                outputs.push(None);
+                continue;
+            }
+            let (amount_out, decimals_out) = if order.chain_from_id == pool_info.meta.chain0_id {
+                (*input * side1 / side0, pool_info.decimals1)
+            } else {
+                (*input * side0 / side1, pool_info.decimals0)
+            };
+            let amount_out = denormalize(
+                Uint256::try_from(amount_out / Uint512::from(FEE_DENOMINATOR))
+                    .map_err(StdError::from)?,
+                decimals_out,
+            );
+            if amount_out < order.min_amount_out {
+                let further = worst.map_or(true, |(j, worst_out)| {
+                    Uint512::from(order.min_amount_out) * Uint512::from(worst_out)
+                        > Uint512::from(orders[j].min_amount_out) * Uint512::from(amount_out)
+                });
+                if further {
+                    worst = Some((i, amount_out));
                 }
-                LIQUIDITY_QUEUE_IDS.save(
-                    deps.storage,
-                    pool_id_key,
-                    &QueueID {
-                        start: 0,
-                        length: 1,
-                    },
-                )?;
-                LIQUIDITY_QUEUE.save(
+            }
+            outputs.push(Some(amount_out));
+        }
+        match worst {
+            Some((i, _)) => filled[i] = false,
+            None => return Ok(outputs),
+        }
+    }
+}
+
+/// Clear a batch of swap orders, paying out the filled ones and refunding the rest.
+fn settle_batch(
+    deps: DepsMut,
+    env: &Env,
+    pool_info: &mut PoolInfo,
+    orders: Vec<SwapOrder>,
+) -> Result<Vec<CosmosMsg<PalomaMsg>>, ContractError> {
+    let outputs = clear_batch(pool_info, &orders)?;
+    let mut messages = vec![];
+    for (order, amount_out) in orders.iter().zip(outputs) {
+        let is_chain0 = order.chain_from_id == pool_info.meta.chain0_id;
+        let amount_out = match amount_out {
+            Some(amount_out) => amount_out,
+            None => {
+                messages.extend(payout(
                     deps.storage,
-                    (pool_id_key, 0u64.to_be_bytes().as_slice()),
-                    &LiquidityQueueElement {
-                        chain_id,
-                        amount,
-                        receiver,
-                    },
-                )?;
+                    env,
+                    PayoutKind::Refund,
+                    order.chain_from_id,
+                    pool_info.pool_id,
+                    order.amount,
+                    &order.sender,
+                )?);
+                continue;
+            }
+        };
+        let chain_to_id = if is_chain0 {
+            pool_info.amount0 += order.amount;
+            pool_info.amount1 -= amount_out;
+            pool_info.meta.chain1_id
+        } else {
+            pool_info.amount1 += order.amount;
+            pool_info.amount0 -= amount_out;
+            pool_info.meta.chain0_id
+        };
+        messages.extend(payout(
+            deps.storage,
+            env,
+            PayoutKind::Swap {
+                chain_from_id: order.chain_from_id,
+                amount_in: order.amount,
+                sender: order.sender.clone(),
+            },
+            chain_to_id,
+            pool_info.pool_id,
+            amount_out,
+            &order.receiver,
+        )?);
+    }
+    SWAP_BATCHES.remove(deps.storage, pool_info.pool_id.to_be_bytes().as_slice());
+    Ok(messages)
+}
+
+fn settle_expired_batch(
+    mut deps: DepsMut,
+    env: Env,
+    pool_id: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let batch = SWAP_BATCHES
+        .may_load(deps.storage, pool_id_key)?
+        .ok_or(BatchNotFound { pool_id })?;
+    let closes = batch_closes(&pool_info, &batch);
+    if closes > env.block.time {
+        return Err(BatchNotClosed { pool_id, closes });
+    }
+    let messages = settle_batch(deps.branch(), &env, &mut pool_info, batch.orders)?;
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(Response::new().add_messages(messages))
+}
+
+/// Mark a relayed deposit or request as processed, rejecting it if it already was.
+fn record_deposit(
+    storage: &mut dyn Storage,
+    chain_id: Uint256,
+    tx_hash: &str,
+    log_index: u64,
+) -> Result<(), ContractError> {
+    let tx_hash = tx_hash.to_lowercase();
+    let binding = chain_id.to_be_bytes();
+    let key = (binding.as_slice(), tx_hash.as_str(), log_index);
+    if PROCESSED_DEPOSITS.has(storage, key) {
+        return Err(DuplicateDeposit {
+            chain_id,
+            tx_hash,
+            log_index,
+        });
+    }
+    PROCESSED_DEPOSITS.save(storage, key, &Empty {})?;
+    Ok(())
+}
+
+/// Pay out of a pool with the factory function matching `kind`, recording the payout as a pending
+/// settlement until the relayer acknowledges or fails it.
+///
+/// On a chain batching payouts, the payout is added to the pool's outbox instead, and a message is
+/// only returned when the outbox fills up and is flushed.
+fn payout(
+    storage: &mut dyn Storage,
+    env: &Env,
+    kind: PayoutKind,
+    chain_id: Uint256,
+    pool_id: Uint256,
+    amount: Uint256,
+    recipient: &str,
+) -> Result<Option<CosmosMsg<PalomaMsg>>, ContractError> {
+    let nonce = SETTLEMENTS_COUNT.may_load(storage)?.unwrap_or_default();
+    SETTLEMENTS_COUNT.save(storage, &(nonce + 1))?;
+    let settlement = Settlement {
+        nonce,
+        pool_id,
+        chain_id,
+        recipient: recipient.to_string(),
+        amount,
+        kind,
+    };
+    let chain_info = POOL_FACTORIES.load(storage, chain_id.to_be_bytes().as_slice())?;
+    if let Some(batch_size) = chain_info.batch_size {
+        let binding = (chain_id.to_be_bytes(), pool_id.to_be_bytes());
+        let outbox_key = (binding.0.as_slice(), binding.1.as_slice());
+        let mut outbox = OUTBOX.may_load(storage, outbox_key)?.unwrap_or_default();
+        outbox.push(settlement);
+        let full = outbox.len() >= batch_size as usize;
+        OUTBOX.save(storage, outbox_key, &outbox)?;
+        if !full {
+            return Ok(None);
+        }
+        return flush_outbox(storage, env, chain_id, pool_id).map(Some);
+    }
+    let transfer = match settlement.kind {
+        PayoutKind::Swap { .. } => Transfer::Swap,
+        PayoutKind::Withdrawal { .. } => Transfer::RemoveLiquidity,
+        PayoutKind::Refund => Transfer::Refund,
+    };
+    let message = factory_message(storage, env, chain_id, |encoder, envelope| {
+        encoder.transfer(transfer, pool_id, amount, recipient, envelope)
+    })?;
+    PENDING_SETTLEMENTS.save(storage, nonce, &settlement)?;
+    Ok(Some(message))
+}
+
+/// Send the payouts in the outbox of a pool on `chain_id` as one batched transfer, aggregating
+/// payouts to the same recipient into one. Deposits are already held by the factory when they are
+/// relayed, so they are not netted against the payouts. Each payout is a pending settlement from
+/// then on.
+fn flush_outbox(
+    storage: &mut dyn Storage,
+    env: &Env,
+    chain_id: Uint256,
+    pool_id: Uint256,
+) -> Result<CosmosMsg<PalomaMsg>, ContractError> {
+    let binding = (chain_id.to_be_bytes(), pool_id.to_be_bytes());
+    let outbox_key = (binding.0.as_slice(), binding.1.as_slice());
+    let outbox = OUTBOX.may_load(storage, outbox_key)?.unwrap_or_default();
+    if outbox.is_empty() {
+        return Err(OutboxEmpty { chain_id, pool_id });
+    }
+    OUTBOX.remove(storage, outbox_key);
+    let mut transfers: Vec<(String, Uint256)> = vec![];
+    for settlement in outbox {
+        match transfers
+            .iter_mut()
+            .find(|(recipient, _)| *recipient == settlement.recipient)
+        {
+            Some((_, amount)) => *amount += settlement.amount,
+            None => transfers.push((settlement.recipient.clone(), settlement.amount)),
+        }
+        PENDING_SETTLEMENTS.save(storage, settlement.nonce, &settlement)?;
+    }
+    factory_message(storage, env, chain_id, |encoder, envelope| {
+        encoder.batch_transfer(pool_id, &transfers, envelope)
+    })
+}
+
+/// Flush the outbox of a pool on `chain_id` ahead of it filling up.
+fn execute_flush_outbox(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    chain_id: Uint256,
+    pool_id: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_relayer(deps.as_ref(), &info)?;
+    let message = flush_outbox(deps.storage, &env, chain_id, pool_id)?;
+    Ok(Response::new().add_message(message))
+}
+
+fn assert_relayer(deps: Deps, info: &MessageInfo) -> Result<(), ContractError> {
+    if RELAYER.may_load(deps.storage)?.flatten().as_ref() != Some(&info.sender) {
+        return Err(Unauthorized {});
+    }
+    Ok(())
+}
+
+fn set_relayer(
+    deps: DepsMut,
+    info: MessageInfo,
+    relayer: Option<Addr>,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_admin(deps.as_ref(), &info)?;
+    RELAYER.save(deps.storage, &relayer)?;
+    Ok(Response::new())
+}
+
+fn load_settlement(deps: Deps, nonce: u64) -> Result<Settlement, ContractError> {
+    PENDING_SETTLEMENTS
+        .may_load(deps.storage, nonce)?
+        .ok_or(SettlementNotFound { nonce })
+}
+
+fn ack_settlement(
+    deps: DepsMut,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_relayer(deps.as_ref(), &info)?;
+    load_settlement(deps.as_ref(), nonce)?;
+    PENDING_SETTLEMENTS.remove(deps.storage, nonce);
+    Ok(Response::new())
+}
+
+/// Roll back a payout which failed on its target chain. Swaps are reversed and their input
+/// refunded, withdrawals return to the reserves and the holder's LP shares. Failed refunds have
+/// nothing to restore. Queued swaps are then retried against the restored reserves.
+fn fail_settlement(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nonce: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    assert_relayer(deps.as_ref(), &info)?;
+    let settlement = load_settlement(deps.as_ref(), nonce)?;
+    PENDING_SETTLEMENTS.remove(deps.storage, nonce);
+    if settlement.kind == PayoutKind::Refund {
+        return Ok(Response::new());
+    }
+    let binding = settlement.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let is_chain0 = settlement.chain_id == pool_info.meta.chain0_id;
+    if is_chain0 {
+        pool_info.amount0 += settlement.amount;
+    } else {
+        pool_info.amount1 += settlement.amount;
+    }
+    let mut messages = vec![];
+    match settlement.kind {
+        PayoutKind::Swap {
+            chain_from_id,
+            amount_in,
+            sender,
+        } => {
+            // Withdrawals since the swap may have paid out part of its input, so the refund is
+            // capped by what is left of the reserve.
+            let reserve_in = if is_chain0 {
+                &mut pool_info.amount1
             } else {
-                LIQUIDITY_QUEUE_IDS.save(deps.storage, pool_id_key, &liquidity_queue_id)?;
+                &mut pool_info.amount0
+            };
+            let refund = amount_in.min(*reserve_in);
+            *reserve_in -= refund;
+            if !refund.is_zero() {
+                messages.extend(payout(
+                    deps.storage,
+                    &env,
+                    PayoutKind::Refund,
+                    chain_from_id,
+                    settlement.pool_id,
+                    refund,
+                    &sender,
+                )?);
+            }
+        }
+        PayoutKind::Withdrawal { holder, liquidity } => {
+            pool_info.total_liquidity += liquidity;
+            credit_liquidity(deps.storage, pool_id_key, holder.as_bytes(), liquidity)?;
+        }
+        PayoutKind::Refund => unreachable!(),
+    }
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    messages.extend(process_swap_queue(deps, &env, settlement.pool_id)?);
+    Ok(Response::new().add_messages(messages))
+}
+
+/// The `LIQUIDITY` holder of an LP owner, with remote addresses in canonical form. Paloma owners
+/// must be valid accounts other than the locked liquidity holder, and can't contain the `/` of a
+/// remote holder.
+fn lp_holder(deps: Deps, owner: LpOwner) -> Result<Addr, ContractError> {
+    match owner {
+        LpOwner::Paloma(addr) => {
+            let invalid = || InvalidAddress {
+                address: addr.to_string(),
+            };
+            let validated = deps
+                .api
+                .addr_validate(addr.as_str())
+                .map_err(|_| invalid())?;
+            if validated == LOCKED_LIQUIDITY_HOLDER || validated.as_str().contains('/') {
+                return Err(invalid());
             }
+            Ok(validated)
+        }
+        LpOwner::Remote { chain_id, address } => {
+            let address = chain_encoder(deps.storage, chain_id)?.canonical_address(&address)?;
+            Ok(Addr::unchecked(format!("{chain_id}/{address}")))
+        }
+    }
+}
+
+/// Perform an action signed by an owner on an EVM chain, after checking the signature, the nonce
+/// and the deadline. The signer owns the shares or queued deposits acted on.
+fn submit_signed_intent(
+    deps: DepsMut,
+    env: Env,
+    chain_id: Uint256,
+    action: SignedAction,
+    nonce: u64,
+    deadline: u64,
+    signature: Binary,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let chain_info = POOL_FACTORIES
+        .may_load(deps.storage, chain_id.to_be_bytes().as_slice())?
+        .ok_or(ChainNotFound { chain_id })?;
+    if chain_info.encoding != Encoding::Evm {
+        return Err(SigningUnsupported { chain_id });
+    }
+    if env.block.time.seconds() > deadline {
+        return Err(Expired { deadline });
+    }
+    let digest = eip712::digest(
+        env.contract.address.as_str(),
+        chain_id,
+        &action,
+        nonce,
+        deadline,
+    );
+    let address = eip712::recover(deps.api, &digest, &signature)?;
+    let holder = lp_holder(deps.as_ref(), LpOwner::Remote { chain_id, address })?;
+    let expected = SIGNER_NONCES
+        .may_load(deps.storage, holder.as_str())?
+        .unwrap_or_default();
+    if nonce != expected {
+        return Err(InvalidNonce { nonce, expected });
+    }
+    SIGNER_NONCES.save(deps.storage, holder.as_str(), &(nonce + 1))?;
+    match action {
+        SignedAction::RemoveLiquidity {
+            pool_id,
+            amount,
+            receiver0,
+            receiver1,
+            min_amount0,
+            min_amount1,
+        } => remove_liquidity(
+            deps,
+            env,
+            holder,
+            pool_id,
+            amount,
+            receiver0,
+            receiver1,
+            min_amount0,
+            min_amount1,
+            deadline,
+        ),
+        SignedAction::CancelQueuedDeposits { pool_id } => {
+            cancel_queued_deposits(deps, env, holder, pool_id)
+        }
+    }
+}
+
+/// The payload encoder of a registered chain.
+fn chain_encoder(
+    storage: &dyn Storage,
+    chain_id: Uint256,
+) -> Result<&'static dyn PayloadEncoder, ContractError> {
+    let chain_info = POOL_FACTORIES
+        .may_load(storage, chain_id.to_be_bytes().as_slice())?
+        .ok_or(ChainNotFound { chain_id })?;
+    Ok(abi::encoder(&chain_info))
+}
+
+/// Call the factory on `chain_id` with the payload built by `encode` using the chain's encoder,
+/// stamped with the chain's next payload nonce and a deadline.
+fn factory_message(
+    storage: &mut dyn Storage,
+    env: &Env,
+    chain_id: Uint256,
+    encode: impl FnOnce(&dyn PayloadEncoder, &Envelope) -> Result<Vec<u8>, ContractError>,
+) -> Result<CosmosMsg<PalomaMsg>, ContractError> {
+    let binding = chain_id.to_be_bytes();
+    let chain_key = binding.as_slice();
+    let chain_info = POOL_FACTORIES.load(storage, chain_key)?;
+    let nonce = CHAIN_NONCES
+        .may_load(storage, chain_key)?
+        .unwrap_or_default();
+    let payload = encode(
+        abi::encoder(&chain_info),
+        &Envelope {
+            nonce,
+            deadline: env.block.time.plus_seconds(PAYLOAD_TTL),
+        },
+    )?;
+    CHAIN_NONCES.save(storage, chain_key, &(nonce + 1))?;
+    Ok(CosmosMsg::Custom(PalomaMsg {
+        job_id: chain_info.factory,
+        payload: Binary(payload),
+    }))
+}
+
+/// Find the pool trading `token_from` on `chain_from_id` against `token_to` on `chain_to_id`.
+fn find_pool_id(
+    deps: Deps,
+    chain_from_id: Uint256,
+    chain_to_id: Uint256,
+    token_from: String,
+    token_to: String,
+) -> Result<Uint256, ContractError> {
+    let token_from = chain_encoder(deps.storage, chain_from_id)?.canonical_token(&token_from)?;
+    let token_to = chain_encoder(deps.storage, chain_to_id)?.canonical_token(&token_to)?;
+    let pool_meta_info = if chain_from_id < chain_to_id {
+        PoolMetaInfo {
+            chain0_id: chain_from_id,
+            chain1_id: chain_to_id,
+            token0: token_from,
+            token1: token_to,
+        }
+    } else {
+        PoolMetaInfo {
+            chain0_id: chain_to_id,
+            chain1_id: chain_from_id,
+            token0: token_to,
+            token1: token_from,
+        }
+    };
+    Ok(POOL_IDS.load(deps.storage, to_binary(&pool_meta_info)?.as_slice())?)
+}
+
+/// Scale a raw token amount with `decimals` to `NORMALIZED_DECIMALS`.
+fn normalize(amount: Uint256, decimals: u8) -> Uint256 {
+    match decimals.cmp(&NORMALIZED_DECIMALS) {
+        Ordering::Less => amount * Uint256::from(10u8).pow((NORMALIZED_DECIMALS - decimals).into()),
+        Ordering::Equal => amount,
+        Ordering::Greater => {
+            amount / Uint256::from(10u8).pow((decimals - NORMALIZED_DECIMALS).into())
+        }
+    }
+}
+
+/// Scale a normalized amount back to a raw token amount with `decimals`.
+fn denormalize(amount: Uint256, decimals: u8) -> Uint256 {
+    match decimals.cmp(&NORMALIZED_DECIMALS) {
+        Ordering::Less => amount / Uint256::from(10u8).pow((NORMALIZED_DECIMALS - decimals).into()),
+        Ordering::Equal => amount,
+        Ordering::Greater => {
+            amount * Uint256::from(10u8).pow((decimals - NORMALIZED_DECIMALS).into())
+        }
+    }
+}
+
+/// Normalized `(reserve_in, reserve_out)` of a pool for a trade in the given direction.
+fn normalized_reserves(pool_info: &PoolInfo, is_chain0: bool) -> (Uint256, Uint256) {
+    let reserve0 = normalize(pool_info.amount0, pool_info.decimals0);
+    let reserve1 = normalize(pool_info.amount1, pool_info.decimals1);
+    if is_chain0 {
+        (reserve0, reserve1)
+    } else {
+        (reserve1, reserve0)
+    }
+}
+
+/// Raw output amount of trading `amount_in` into the pool on the constant product curve.
+fn get_amount_out(
+    pool_info: &PoolInfo,
+    is_chain0: bool,
+    amount_in: Uint256,
+) -> Result<Uint256, ContractError> {
+    let (reserve_in, reserve_out) = normalized_reserves(pool_info, is_chain0);
+    if reserve_in.is_zero() || reserve_out.is_zero() {
+        return Err(EmptyPool {
+            pool_id: pool_info.pool_id,
+        });
+    }
+    let (decimals_in, decimals_out) = if is_chain0 {
+        (pool_info.decimals0, pool_info.decimals1)
+    } else {
+        (pool_info.decimals1, pool_info.decimals0)
+    };
+    let amount_in_with_fee = Uint512::from(normalize(amount_in, decimals_in))
+        * Uint512::from(FEE_DENOMINATOR - FEE_NUMERATOR);
+    let amount_out = amount_in_with_fee * Uint512::from(reserve_out)
+        / (Uint512::from(reserve_in) * Uint512::from(FEE_DENOMINATOR) + amount_in_with_fee);
+    Ok(denormalize(
+        Uint256::try_from(amount_out).map_err(StdError::from)?,
+        decimals_out,
+    ))
+}
+
+/// Raw input amount needed to receive `amount_out` from the pool on the constant product curve,
+/// rounded up.
+fn get_amount_in(
+    pool_info: &PoolInfo,
+    is_chain0: bool,
+    amount_out: Uint256,
+) -> Result<Uint256, ContractError> {
+    let (reserve_in, reserve_out) = normalized_reserves(pool_info, is_chain0);
+    let (decimals_in, decimals_out) = if is_chain0 {
+        (pool_info.decimals0, pool_info.decimals1)
+    } else {
+        (pool_info.decimals1, pool_info.decimals0)
+    };
+    let normalized_out = normalize(amount_out, decimals_out);
+    if reserve_in.is_zero() || normalized_out >= reserve_out {
+        return Err(InsufficientLiquidity {
+            available: denormalize(reserve_out, decimals_out),
+            requested: amount_out,
+        });
+    }
+    let normalized_in =
+        Uint512::from(reserve_in) * Uint512::from(normalized_out) * Uint512::from(FEE_DENOMINATOR)
+            / (Uint512::from(reserve_out - normalized_out)
+                * Uint512::from(FEE_DENOMINATOR - FEE_NUMERATOR))
+            + Uint512::one();
+    let normalized_in = Uint256::try_from(normalized_in).map_err(StdError::from)?;
+    let amount_in = denormalize(normalized_in, decimals_in);
+    if normalize(amount_in, decimals_in) < normalized_in {
+        Ok(amount_in + Uint256::one())
+    } else {
+        Ok(amount_in)
+    }
+}
+
+/// Mint liquidity for depositing raw `amount0` and `amount1`, adding them to the reserves.
+fn mint_liquidity(pool_info: &mut PoolInfo, amount0: Uint256, amount1: Uint256) -> Uint256 {
+    let normalized0 = normalize(amount0, pool_info.decimals0);
+    let normalized1 = normalize(amount1, pool_info.decimals1);
+    let liquidity = if pool_info.total_liquidity.is_zero() {
+        Uint256::try_from((Uint512::from(normalized0) * Uint512::from(normalized1)).isqrt())
+            .unwrap()
+    } else {
+        let total_liquidity = pool_info.total_liquidity;
+        let reserve0 = normalize(pool_info.amount0, pool_info.decimals0);
+        let reserve1 = normalize(pool_info.amount1, pool_info.decimals1);
+        std::cmp::min(
+            normalized0.multiply_ratio(total_liquidity, reserve0),
+            normalized1.multiply_ratio(total_liquidity, reserve1),
+        )
+    };
+    pool_info.amount0 += amount0;
+    pool_info.amount1 += amount1;
+    pool_info.total_liquidity += liquidity;
+    liquidity
+}
+
+/// Amount of a single-sided deposit to swap so the remainder and the swap output match the pool
+/// ratio after the swap.
+fn zap_swap_amount(reserve_in: Uint256, amount: Uint256) -> Uint256 {
+    // Solves (amount - s) / (reserve_in + s) = out(s) / (reserve_out - out(s)) for the fee-adjusted
+    // constant product output out(s).
+    let fee_denominator = Uint512::from(FEE_DENOMINATOR);
+    let fee_multiplier = Uint512::from(FEE_DENOMINATOR - FEE_NUMERATOR);
+    let b = Uint512::from(2 * FEE_DENOMINATOR - FEE_NUMERATOR);
+    let reserve_in = Uint512::from(reserve_in);
+    let root = (reserve_in
+        * (reserve_in * b * b
+            + Uint512::from(4u8) * Uint512::from(amount) * fee_multiplier * fee_denominator))
+        .isqrt();
+    Uint256::try_from((root - reserve_in * b) / (Uint512::from(2u8) * fee_multiplier)).unwrap()
+}
+
+/// Swap part of a single-sided deposit through the pool and mint liquidity for the balanced
+/// remainder.
+fn zap_in(
+    pool_info: &mut PoolInfo,
+    is_chain0: bool,
+    amount: Uint256,
+) -> Result<Uint256, ContractError> {
+    if pool_info.total_liquidity.is_zero() {
+        return Err(EmptyPool {
+            pool_id: pool_info.pool_id,
+        });
+    }
+    let reserve_in = if is_chain0 {
+        pool_info.amount0
+    } else {
+        pool_info.amount1
+    };
+    let swap_amount = zap_swap_amount(reserve_in, amount);
+    let swapped = get_amount_out(pool_info, is_chain0, swap_amount)?;
+    let liquidity = if is_chain0 {
+        pool_info.amount0 += swap_amount;
+        pool_info.amount1 -= swapped;
+        mint_liquidity(pool_info, amount - swap_amount, swapped)
+    } else {
+        pool_info.amount1 += swap_amount;
+        pool_info.amount0 -= swapped;
+        mint_liquidity(pool_info, swapped, amount - swap_amount)
+    };
+    if liquidity.is_zero() {
+        return Err(InsufficientLiquidityMinted { liquidity });
+    }
+    Ok(liquidity)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn register_deposit_intent(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: Uint256,
+    amount0: Uint256,
+    amount1: Uint256,
+    sender0: String,
+    sender1: String,
+    tolerance_bps: u16,
+    expiry: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let pool_info = pools_info().load(deps.storage, pool_id.to_be_bytes().as_slice())?;
+    if pool_info.total_liquidity.is_zero() {
+        return Err(EmptyPool { pool_id });
+    }
+    if env.block.time.seconds() >= expiry {
+        return Err(Expired { deadline: expiry });
+    }
+    let sender0 =
+        chain_encoder(deps.storage, pool_info.meta.chain0_id)?.canonical_address(&sender0)?;
+    let sender1 =
+        chain_encoder(deps.storage, pool_info.meta.chain1_id)?.canonical_address(&sender1)?;
+    let intent_id = DEPOSIT_INTENTS_COUNT
+        .may_load(deps.storage)?
+        .unwrap_or_default();
+    DEPOSIT_INTENTS_COUNT.save(deps.storage, &(intent_id + 1))?;
+    DEPOSIT_INTENTS.save(
+        deps.storage,
+        intent_id,
+        &DepositIntent {
+            pool_id,
+            owner: info.sender,
+            amount0,
+            amount1,
+            reserve0: pool_info.amount0,
+            reserve1: pool_info.amount1,
+            tolerance_bps,
+            expiry: Timestamp::from_seconds(expiry),
+            sender0,
+            sender1,
+            arrived0: false,
+            arrived1: false,
+        },
+    )?;
+    Ok(Response::new()
+        .add_attribute("intent_id", intent_id.to_string())
+        .set_data(to_binary(&intent_id)?))
+}
+
+/// Record one relayed leg of a deposit intent, minting both legs together once they arrived. The
+/// part of a leg above the pool ratio is refunded.
+fn add_intent_leg(
+    deps: DepsMut,
+    env: Env,
+    mut pool_info: PoolInfo,
+    intent_id: u64,
+    is_chain0: bool,
+    amount: Uint256,
+    sender: String,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let intent = DEPOSIT_INTENTS.may_load(deps.storage, intent_id)?;
+    let mut intent = match intent.filter(|intent| intent.pool_id == pool_info.pool_id) {
+        Some(intent) => intent,
+        None => return refund_leg(deps.storage, &env, &pool_info, is_chain0, amount, &sender),
+    };
+    let (expected, expected_sender, arrived) = if is_chain0 {
+        (intent.amount0, &intent.sender0, &mut intent.arrived0)
+    } else {
+        (intent.amount1, &intent.sender1, &mut intent.arrived1)
+    };
+    if expected != amount || *expected_sender != sender || *arrived {
+        return refund_leg(deps.storage, &env, &pool_info, is_chain0, amount, &sender);
+    }
+    *arrived = true;
+
+    if intent.expiry <= env.block.time {
+        DEPOSIT_INTENTS.remove(deps.storage, intent_id);
+        return Ok(Response::new().add_messages(intent_refunds(deps.storage, &env, &intent)?));
+    }
+    if !intent.arrived0 || !intent.arrived1 {
+        DEPOSIT_INTENTS.save(deps.storage, intent_id, &intent)?;
+        return Ok(Response::new());
+    }
+
+    DEPOSIT_INTENTS.remove(deps.storage, intent_id);
+    // Compare the current price with the one at registration, reserve1 / reserve0.
+    let current = Uint512::from(pool_info.amount1) * Uint512::from(intent.reserve0);
+    let registered = Uint512::from(intent.reserve1) * Uint512::from(pool_info.amount0);
+    let deviation = if current > registered {
+        current - registered
+    } else {
+        registered - current
+    };
+    if deviation * Uint512::from(BPS_DENOMINATOR) > registered * Uint512::from(intent.tolerance_bps)
+    {
+        return Ok(Response::new().add_messages(intent_refunds(deps.storage, &env, &intent)?));
+    }
+    let amount1 = intent
+        .amount0
+        .multiply_ratio(pool_info.amount1, pool_info.amount0);
+    let (amount0, amount1) = if amount1 <= intent.amount1 {
+        (intent.amount0, amount1)
+    } else {
+        let amount0 = intent
+            .amount1
+            .multiply_ratio(pool_info.amount0, pool_info.amount1);
+        (amount0, intent.amount1)
+    };
+    let liquidity = mint_liquidity(&mut pool_info, amount0, amount1);
+    let binding = pool_info.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    credit_liquidity(
+        deps.storage,
+        pool_id_key,
+        intent.owner.as_bytes(),
+        liquidity,
+    )?;
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    let mut messages = vec![];
+    for (chain_id, excess, sender) in [
+        (
+            pool_info.meta.chain0_id,
+            intent.amount0 - amount0,
+            &intent.sender0,
+        ),
+        (
+            pool_info.meta.chain1_id,
+            intent.amount1 - amount1,
+            &intent.sender1,
+        ),
+    ] {
+        if !excess.is_zero() {
+            messages.extend(payout(
+                deps.storage,
+                &env,
+                PayoutKind::Refund,
+                chain_id,
+                pool_info.pool_id,
+                excess,
+                sender,
+            )?);
+        }
+    }
+    Ok(Response::new().add_messages(messages))
+}
+
+/// Refund a relayed leg which doesn't fit its intent. The leg has already arrived on its chain, so
+/// it is returned rather than rejected.
+fn refund_leg(
+    storage: &mut dyn Storage,
+    env: &Env,
+    pool_info: &PoolInfo,
+    is_chain0: bool,
+    amount: Uint256,
+    sender: &str,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let chain_id = if is_chain0 {
+        pool_info.meta.chain0_id
+    } else {
+        pool_info.meta.chain1_id
+    };
+    Ok(Response::new().add_messages(payout(
+        storage,
+        env,
+        PayoutKind::Refund,
+        chain_id,
+        pool_info.pool_id,
+        amount,
+        sender,
+    )?))
+}
+
+/// Refund every leg of a deposit intent which has arrived.
+fn intent_refunds(
+    storage: &mut dyn Storage,
+    env: &Env,
+    intent: &DepositIntent,
+) -> Result<Vec<CosmosMsg<PalomaMsg>>, ContractError> {
+    let pool_info = pools_info().load(storage, intent.pool_id.to_be_bytes().as_slice())?;
+    let mut messages = vec![];
+    for (chain_id, amount, sender, arrived) in [
+        (
+            pool_info.meta.chain0_id,
+            intent.amount0,
+            &intent.sender0,
+            intent.arrived0,
+        ),
+        (
+            pool_info.meta.chain1_id,
+            intent.amount1,
+            &intent.sender1,
+            intent.arrived1,
+        ),
+    ] {
+        if arrived {
+            messages.extend(payout(
+                storage,
+                env,
+                PayoutKind::Refund,
+                chain_id,
+                intent.pool_id,
+                amount,
+                sender,
+            )?);
+        }
+    }
+    Ok(messages)
+}
+
+fn refund_deposit_intent(
+    deps: DepsMut,
+    env: Env,
+    intent_id: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let intent = DEPOSIT_INTENTS
+        .may_load(deps.storage, intent_id)?
+        .ok_or(IntentNotFound { intent_id })?;
+    if intent.expiry > env.block.time {
+        return Err(IntentNotExpired {
+            intent_id,
+            expiry: intent.expiry,
+        });
+    }
+    DEPOSIT_INTENTS.remove(deps.storage, intent_id);
+    Ok(Response::new().add_messages(intent_refunds(deps.storage, &env, &intent)?))
+}
+
+/// Add `amount` to the LP shares of `holder` in a pool.
+fn credit_liquidity(
+    storage: &mut dyn Storage,
+    pool_id_key: &[u8],
+    holder: &[u8],
+    amount: Uint256,
+) -> StdResult<()> {
+    LIQUIDITY.update(
+        storage,
+        (pool_id_key, holder),
+        |liquidity| -> StdResult<_> { Ok(liquidity.unwrap_or_default() + amount) },
+    )?;
+    Ok(())
+}
+
+/// Credit half of `amount` to each holder, locking the odd remainder so the shares stay in sync
+/// with `total_liquidity`.
+fn split_liquidity(
+    storage: &mut dyn Storage,
+    pool_id_key: &[u8],
+    holders: [&Addr; 2],
+    amount: Uint256,
+) -> StdResult<()> {
+    let half = amount / Uint256::from(2u8);
+    for holder in holders {
+        credit_liquidity(storage, pool_id_key, holder.as_bytes(), half)?;
+    }
+    let remainder = amount - half - half;
+    if !remainder.is_zero() {
+        credit_liquidity(
+            storage,
+            pool_id_key,
+            LOCKED_LIQUIDITY_HOLDER.as_bytes(),
+            remainder,
+        )?;
+    }
+    Ok(())
+}
+
+/// Load up to `limit` queued deposits of a pool with their sequence numbers, in arrival order.
+fn load_queue(
+    storage: &dyn Storage,
+    pool_id_key: &[u8],
+    limit: usize,
+) -> StdResult<Vec<(u64, LiquidityQueueElement)>> {
+    liquidity_queue()
+        .prefix(pool_id_key)
+        .range(storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+/// The oldest queued deposit of a pool, if any.
+fn queue_head(
+    storage: &dyn Storage,
+    pool_id_key: &[u8],
+) -> StdResult<Option<LiquidityQueueElement>> {
+    let head = liquidity_queue()
+        .prefix(pool_id_key)
+        .range(storage, None, None, Order::Ascending)
+        .next()
+        .transpose()?;
+    Ok(head.map(|(_, element)| element))
+}
+
+/// Append a deposit to the liquidity queue of a pool.
+fn push_queue(
+    storage: &mut dyn Storage,
+    pool_id_key: &[u8],
+    element: &LiquidityQueueElement,
+) -> StdResult<()> {
+    let seq = LIQUIDITY_QUEUE_SEQ
+        .may_load(storage, pool_id_key)?
+        .unwrap_or_default();
+    LIQUIDITY_QUEUE_SEQ.save(storage, pool_id_key, &(seq + 1))?;
+    liquidity_queue().save(storage, (pool_id_key, seq), element)
+}
+
+/// Remove and return every queued deposit of a pool.
+fn clear_queue(
+    storage: &mut dyn Storage,
+    pool_id_key: &[u8],
+) -> StdResult<Vec<LiquidityQueueElement>> {
+    let queue = load_queue(storage, pool_id_key, usize::MAX)?;
+    let mut elements = vec![];
+    for (seq, element) in queue {
+        liquidity_queue().remove(storage, (pool_id_key, seq))?;
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Split `needed` of the queued token across queued deposits of `amounts` according to `policy`.
+fn allocate_matches(policy: &MatchingPolicy, amounts: &[Uint256], needed: Uint256) -> Vec<Uint256> {
+    let total: Uint256 = amounts.iter().sum();
+    if total <= needed {
+        return amounts.to_vec();
+    }
+    let mut fills = match policy {
+        MatchingPolicy::Fifo => vec![Uint256::zero(); amounts.len()],
+        MatchingPolicy::ProRata => amounts
+            .iter()
+            .map(|amount| amount.multiply_ratio(needed, total))
+            .collect(),
+        MatchingPolicy::RoundRobin { cap } => {
+            // Find the number of full rounds the deposit covers.
+            let filled = |rounds: Uint256| -> Uint256 {
+                let limit = cap.saturating_mul(rounds);
+                amounts.iter().map(|amount| (*amount).min(limit)).sum()
+            };
+            let max = amounts.iter().max().copied().unwrap_or_default();
+            let (mut low, mut high) = (Uint256::zero(), max / *cap + Uint256::one());
+            while high - low > Uint256::one() {
+                let mid = (low + high) / Uint256::from(2u8);
+                if filled(mid) <= needed {
+                    low = mid;
+                } else {
+                    high = mid;
+                }
+            }
+            let limit = cap.saturating_mul(low);
+            amounts.iter().map(|amount| (*amount).min(limit)).collect()
+        }
+    };
+    // Hand out the rest in arrival order, one partial round for round-robin.
+    let round = match policy {
+        MatchingPolicy::RoundRobin { cap } => *cap,
+        _ => Uint256::MAX,
+    };
+    let mut remaining = needed - fills.iter().sum::<Uint256>();
+    for (fill, amount) in fills.iter_mut().zip(amounts) {
+        let extra = (*amount - *fill).min(round).min(remaining);
+        *fill += extra;
+        remaining -= extra;
+    }
+    fills
+}
+
+/// Match a counter-side `deposit` against the oldest `MAX_MATCH_ITERATIONS` queued deposits of a
+/// pool according to its matching policy. Whatever the queue cannot absorb is queued in their
+/// place, or left in `MATCHING` for `ContinueMatching` if more deposits are queued. Pro-rata
+/// shares are taken over the whole queue, in a round carried across batches in `MATCHING_SHARE`.
+fn match_queue(
+    storage: &mut dyn Storage,
+    pool_info: &mut PoolInfo,
+    is_chain0: bool,
+    mut deposit: LiquidityQueueElement,
+) -> Result<(), ContractError> {
+    let binding = pool_info.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let (reserve_in, reserve_queue) = if is_chain0 {
+        (pool_info.amount0, pool_info.amount1)
+    } else {
+        (pool_info.amount1, pool_info.amount0)
+    };
+    let mut share = MATCHING_SHARE.may_load(storage, pool_id_key)?;
+    MATCHING_SHARE.remove(storage, pool_id_key);
+    let needed = deposit.amount.multiply_ratio(reserve_queue, reserve_in);
+    if needed.is_zero() {
+        return Err(DepositTooSmall {
+            amount: deposit.amount,
+        });
+    }
+    if share.is_none() && matches!(pool_info.matching_policy, MatchingPolicy::ProRata) {
+        share = Some(MatchingShare {
+            needed,
+            queued: if is_chain0 {
+                pool_info.pending_amount1
+            } else {
+                pool_info.pending_amount0
+            },
+            next: 0,
+            end: LIQUIDITY_QUEUE_SEQ
+                .may_load(storage, pool_id_key)?
+                .unwrap_or_default(),
+        });
+    }
+    let (start, end) = share
+        .as_ref()
+        .map_or((0, u64::MAX), |share| (share.next, share.end));
+    let mut queue = liquidity_queue()
+        .prefix(pool_id_key)
+        .range(
+            storage,
+            Some(Bound::inclusive(start)),
+            Some(Bound::exclusive(end)),
+            Order::Ascending,
+        )
+        .take(MAX_MATCH_ITERATIONS + 1)
+        .collect::<StdResult<Vec<_>>>()?;
+    let more = queue.len() > MAX_MATCH_ITERATIONS;
+    queue.truncate(MAX_MATCH_ITERATIONS);
+    let amounts: Vec<Uint256> = queue.iter().map(|(_, element)| element.amount).collect();
+    let queued: Uint256 = amounts.iter().sum();
+    // A pro-rata round fills this batch at the ratio of the whole round.
+    let batch_needed = match &share {
+        Some(share) if !share.queued.is_zero() => {
+            needed.min(queued.multiply_ratio(share.needed, share.queued))
+        }
+        _ => needed,
+    };
+    let fills = allocate_matches(&pool_info.matching_policy, &amounts, batch_needed);
+    let matched: Uint256 = fills.iter().sum();
+    // A fully matched deposit is used up, the last fill taking the rounding dust.
+    let last = if matched == needed {
+        fills.iter().rposition(|fill| !fill.is_zero())
+    } else {
+        None
+    };
+    for (i, ((seq, element), fill)) in queue.iter_mut().zip(fills).enumerate() {
+        if fill.is_zero() {
+            continue;
         }
+        let input = if Some(i) == last {
+            deposit.amount
+        } else {
+            fill.multiply_ratio(reserve_in, reserve_queue)
+        };
+        deposit.amount -= input;
+        element.amount -= fill;
+        let liquidity = if is_chain0 {
+            mint_liquidity(pool_info, input, fill)
+        } else {
+            mint_liquidity(pool_info, fill, input)
+        };
+        split_liquidity(
+            storage,
+            pool_id_key,
+            [&deposit.receiver, &element.receiver],
+            liquidity,
+        )?;
+        if element.amount.is_zero() {
+            liquidity_queue().remove(storage, (pool_id_key, *seq))?;
+        } else {
+            liquidity_queue().save(storage, (pool_id_key, *seq), element)?;
+        }
+    }
+    if is_chain0 {
+        pool_info.pending_amount1 -= matched;
+    } else {
+        pool_info.pending_amount0 -= matched;
+    }
+    if deposit.amount.is_zero() {
+        return Ok(());
+    }
+    if is_chain0 {
+        pool_info.pending_amount0 += deposit.amount;
+    } else {
+        pool_info.pending_amount1 += deposit.amount;
+    }
+    match (share, queue.last()) {
+        // The round goes on with the next batch.
+        (Some(mut share), Some((seq, _))) if more => {
+            share.needed -= matched;
+            share.queued -= queued;
+            share.next = seq + 1;
+            MATCHING_SHARE.save(storage, pool_id_key, &share)?;
+            MATCHING.save(storage, pool_id_key, &deposit)?;
+        }
+        _ if queue_head(storage, pool_id_key)?.is_some() => {
+            MATCHING.save(storage, pool_id_key, &deposit)?;
+        }
+        _ => push_queue(storage, pool_id_key, &deposit)?,
+    }
+    Ok(())
+}
+
+fn continue_matching(
+    deps: DepsMut,
+    env: Env,
+    pool_id: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let deposit = match MATCHING.may_load(deps.storage, pool_id_key)? {
+        Some(deposit) => {
+            MATCHING.remove(deps.storage, pool_id_key);
+            deposit
+        }
+        None => {
+            let (seq, deposit) = MATCHING_BACKLOG
+                .prefix(pool_id_key)
+                .range(deps.storage, None, None, Order::Ascending)
+                .next()
+                .transpose()?
+                .ok_or(NoMatchingInProgress { pool_id })?;
+            MATCHING_BACKLOG.remove(deps.storage, (pool_id_key, seq));
+            deposit
+        }
+    };
+    // With no counter-side deposits left, the deposit waits in the queue.
+    if !matches!(queue_head(deps.storage, pool_id_key)?, Some(head) if head.chain_id != deposit.chain_id)
+    {
+        MATCHING_SHARE.remove(deps.storage, pool_id_key);
+        push_queue(deps.storage, pool_id_key, &deposit)?;
+        return Ok(Response::new());
+    }
+    let is_chain0 = deposit.chain_id == pool_info.meta.chain0_id;
+    if is_chain0 {
+        pool_info.pending_amount0 -= deposit.amount;
     } else {
-        LIQUIDITY_QUEUE.save(
+        pool_info.pending_amount1 -= deposit.amount;
+    }
+    let messages = match match_queue(deps.storage, &mut pool_info, is_chain0, deposit.clone()) {
+        // A remainder too small to match anything is returned to its sender.
+        Err(DepositTooSmall { .. }) => Vec::from_iter(payout(
             deps.storage,
-            (pool_id_key, 0u64.to_be_bytes().as_slice()),
-            &LiquidityQueueElement {
-                chain_id,
-                amount,
-                receiver,
-            },
-        )?;
-        liquidity_queue_id.length = 1;
-        LIQUIDITY_QUEUE_IDS.save(deps.storage, pool_id_key, &liquidity_queue_id)?;
-        if is_chain0 {
-            pool_info.pending_amount0 += amount;
+            &env,
+            PayoutKind::Refund,
+            deposit.chain_id,
+            pool_id,
+            deposit.amount,
+            &deposit.sender,
+        )?),
+        result => {
+            result?;
+            vec![]
+        }
+    };
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(Response::new().add_messages(messages))
+}
+
+fn cancel_queued_deposits(
+    deps: DepsMut,
+    env: Env,
+    owner: Addr,
+    pool_id: Uint256,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let queued = liquidity_queue()
+        .idx
+        .owner
+        .prefix(owner.clone())
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut messages = vec![];
+    for ((key, seq), element) in queued {
+        if key != pool_id_key {
+            continue;
+        }
+        liquidity_queue().remove(deps.storage, (pool_id_key, seq))?;
+        if element.chain_id == pool_info.meta.chain0_id {
+            pool_info.pending_amount0 -= element.amount;
         } else {
-            pool_info.pending_amount1 += amount;
+            pool_info.pending_amount1 -= element.amount;
         }
+        messages.extend(payout(
+            deps.storage,
+            &env,
+            PayoutKind::Refund,
+            element.chain_id,
+            pool_id,
+            element.amount,
+            &element.sender,
+        )?);
     }
-    POOLS_INFO.save(deps.storage, pool_id_key, &pool_info)?;
-    Ok(Response::new())
+    if messages.is_empty() {
+        return Err(QueuedDepositNotFound { pool_id, owner });
+    }
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(Response::new().add_messages(messages))
 }
 
 #[allow(clippy::too_many_arguments)]
-fn remove_liquidity(
+fn add_liquidity(
     deps: DepsMut,
-    info: MessageInfo,
-    chain0_id: Uint256,
-    chain1_id: Uint256,
-    token0: String,
-    token1: String,
-    receiver0: String,
-    receiver1: String,
+    env: Env,
+    pool_id: Uint256,
+    chain_id: Uint256,
+    token: String,
     amount: Uint256,
+    sender: String,
+    receiver: Addr,
+    zap: Option<ZapIn>,
+    intent_id: Option<u64>,
 ) -> Result<Response<PalomaMsg>, ContractError> {
-    assert!(chain0_id < chain1_id);
-    let pool_meta_info = PoolMetaInfo {
-        chain0_id,
-        chain1_id,
-        token0,
-        token1,
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    if amount.is_zero() {
+        return Err(ZeroAmount {});
+    }
+    let encoder = chain_encoder(deps.storage, chain_id)?;
+    let token = encoder.canonical_token(&token)?;
+    let sender = encoder.canonical_address(&sender)?;
+    let is_chain0 = if pool_info.meta.chain0_id == chain_id && pool_info.meta.token0 == token {
+        true
+    } else if pool_info.meta.chain1_id == chain_id && pool_info.meta.token1 == token {
+        false
+    } else {
+        return Err(InvalidToken { token });
     };
-    let binding = to_binary(&pool_meta_info)?;
-    let meta_info_key = binding.as_slice();
-    let pool_id = POOL_IDS.load(deps.storage, meta_info_key)?;
+
+    if let Some(intent_id) = intent_id {
+        return add_intent_leg(deps, env, pool_info, intent_id, is_chain0, amount, sender);
+    }
+
+    if let Some(zap) = zap {
+        let liquidity = zap_in(&mut pool_info, is_chain0, amount)?;
+        if liquidity < zap.min_liquidity {
+            return Err(SlippageExceeded {
+                amount: liquidity,
+                limit: zap.min_liquidity,
+            });
+        }
+        credit_liquidity(deps.storage, pool_id_key, receiver.as_bytes(), liquidity)?;
+        pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+        return Ok(Response::new());
+    }
+
+    if pool_info.total_liquidity.is_zero() {
+        let init_depositor = if is_chain0 {
+            &pool_info.chain0_init_depositor
+        } else {
+            &pool_info.chain1_init_depositor
+        };
+        if sender != *init_depositor {
+            return Err(Unauthorized {});
+        }
+    }
+
+    let deposit = LiquidityQueueElement {
+        chain_id,
+        amount,
+        sender,
+        receiver,
+    };
+    let backlog = MATCHING_BACKLOG.prefix(pool_id_key);
+    let backlogged = backlog
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .next()
+        .is_some();
+    if MATCHING.has(deps.storage, pool_id_key) || backlogged {
+        // The deposit waits its turn behind the matching in progress: counter-side deposits in
+        // the backlog, others in the queue.
+        match queue_head(deps.storage, pool_id_key)? {
+            Some(head) if head.chain_id != chain_id => {
+                let seq = LIQUIDITY_QUEUE_SEQ
+                    .may_load(deps.storage, pool_id_key)?
+                    .unwrap_or_default();
+                LIQUIDITY_QUEUE_SEQ.save(deps.storage, pool_id_key, &(seq + 1))?;
+                MATCHING_BACKLOG.save(deps.storage, (pool_id_key, seq), &deposit)?;
+            }
+            _ => push_queue(deps.storage, pool_id_key, &deposit)?,
+        }
+        if is_chain0 {
+            pool_info.pending_amount0 += amount;
+        } else {
+            pool_info.pending_amount1 += amount;
+        }
+        pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+        return Ok(Response::new());
+    }
+    match queue_head(deps.storage, pool_id_key)? {
+        Some(head) if head.chain_id != chain_id && pool_info.total_liquidity.is_zero() => {
+            let queued = if is_chain0 {
+                pool_info.pending_amount1
+            } else {
+                pool_info.pending_amount0
+            };
+            let liquidity = if is_chain0 {
+                let pending_amount1 = pool_info.pending_amount1;
+                mint_liquidity(&mut pool_info, amount, pending_amount1)
+            } else {
+                let pending_amount0 = pool_info.pending_amount0;
+                mint_liquidity(&mut pool_info, pending_amount0, amount)
+            };
+            let min_liquidity = Uint256::from(MIN_LIQUIDITY);
+            if liquidity <= min_liquidity {
+                return Err(InsufficientLiquidityMinted { liquidity });
+            }
+            credit_liquidity(
+                deps.storage,
+                pool_id_key,
+                LOCKED_LIQUIDITY_HOLDER.as_bytes(),
+                min_liquidity,
+            )?;
+            // Half goes to this deposit and half to the queued deposits in proportion to their
+            // amounts, locking the rounding remainder.
+            let liquidity = liquidity - min_liquidity;
+            let half = liquidity / Uint256::from(2u8);
+            credit_liquidity(deps.storage, pool_id_key, deposit.receiver.as_bytes(), half)?;
+            let mut credited = half;
+            for element in clear_queue(deps.storage, pool_id_key)? {
+                let share = half.multiply_ratio(element.amount, queued);
+                if !share.is_zero() {
+                    credit_liquidity(
+                        deps.storage,
+                        pool_id_key,
+                        element.receiver.as_bytes(),
+                        share,
+                    )?;
+                    credited += share;
+                }
+            }
+            if credited < liquidity {
+                credit_liquidity(
+                    deps.storage,
+                    pool_id_key,
+                    LOCKED_LIQUIDITY_HOLDER.as_bytes(),
+                    liquidity - credited,
+                )?;
+            }
+            pool_info.pending_amount0 = Uint256::zero();
+            pool_info.pending_amount1 = Uint256::zero();
+        }
+        Some(head) if head.chain_id != chain_id => {
+            match_queue(deps.storage, &mut pool_info, is_chain0, deposit)?;
+        }
+        _ => {
+            push_queue(deps.storage, pool_id_key, &deposit)?;
+            if is_chain0 {
+                pool_info.pending_amount0 += amount;
+            } else {
+                pool_info.pending_amount1 += amount;
+            }
+        }
+    }
+    pools_info().save(deps.storage, pool_id_key, &pool_info)?;
+    Ok(Response::new())
+}
+
+/// Compute burning `amount` LP shares of `holder`, removing the proportional reserves from the
+/// pool. Returns the updated pool, the removed `(amount0, amount1)` and the holder's remaining
+/// shares, to be saved with `save_burn` once the caller's checks pass.
+fn burn_liquidity(
+    deps: Deps,
+    env: &Env,
+    holder: &Addr,
+    pool_id: Uint256,
+    amount: Uint256,
+    deadline: u64,
+) -> Result<(PoolInfo, Uint256, Uint256, Uint256), ContractError> {
+    if env.block.time.seconds() > deadline {
+        return Err(Expired { deadline });
+    }
     let binding = pool_id.to_be_bytes();
     let pool_id_key = binding.as_slice();
-    let mut pool_info = POOLS_INFO.load(deps.storage, pool_id_key)?;
+    let liquidity = LIQUIDITY
+        .may_load(deps.storage, (pool_id_key, holder.as_bytes()))?
+        .unwrap_or_default();
+    if amount.is_zero() || amount > liquidity {
+        return Err(InsufficientLiquidity {
+            available: liquidity,
+            requested: amount,
+        });
+    }
+
+    let mut pool_info = pools_info().load(deps.storage, pool_id_key)?;
     let amount0 = pool_info
         .amount0
         .multiply_ratio(amount, pool_info.total_liquidity);
     let amount1 = pool_info
         .amount1
         .multiply_ratio(amount, pool_info.total_liquidity);
-    pool_info.amount0 = pool_info.amount0.checked_sub(amount0).unwrap();
-    pool_info.amount1 = pool_info.amount1.checked_sub(amount1).unwrap();
-    pool_info.total_liquidity = pool_info.total_liquidity.checked_sub(amount).unwrap();
-    POOLS_INFO.save(deps.storage, pool_id_key, &pool_info)?;
-    LIQUIDITY.update(
+    pool_info.amount0 -= amount0;
+    pool_info.amount1 -= amount1;
+    pool_info.total_liquidity -= amount;
+    Ok((pool_info, amount0, amount1, liquidity - amount))
+}
+
+/// Save a pool and the remaining LP shares of `holder` after `burn_liquidity`.
+fn save_burn(
+    storage: &mut dyn Storage,
+    holder: &Addr,
+    pool_info: &PoolInfo,
+    remaining: Uint256,
+) -> StdResult<()> {
+    let binding = pool_info.pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    pools_info().save(storage, pool_id_key, pool_info)?;
+    if remaining.is_zero() {
+        LIQUIDITY.remove(storage, (pool_id_key, holder.as_bytes()));
+    } else {
+        LIQUIDITY.save(storage, (pool_id_key, holder.as_bytes()), &remaining)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn remove_liquidity(
+    deps: DepsMut,
+    env: Env,
+    holder: Addr,
+    pool_id: Uint256,
+    amount: Uint256,
+    receiver0: String,
+    receiver1: String,
+    min_amount0: Uint256,
+    min_amount1: Uint256,
+    deadline: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let (pool_info, amount0, amount1, remaining) =
+        burn_liquidity(deps.as_ref(), &env, &holder, pool_id, amount, deadline)?;
+    let receiver0 =
+        chain_encoder(deps.storage, pool_info.meta.chain0_id)?.canonical_address(&receiver0)?;
+    let receiver1 =
+        chain_encoder(deps.storage, pool_info.meta.chain1_id)?.canonical_address(&receiver1)?;
+    if amount0 < min_amount0 {
+        return Err(SlippageExceeded {
+            amount: amount0,
+            limit: min_amount0,
+        });
+    }
+    if amount1 < min_amount1 {
+        return Err(SlippageExceeded {
+            amount: amount1,
+            limit: min_amount1,
+        });
+    }
+    save_burn(deps.storage, &holder, &pool_info, remaining)?;
+
+    // Each leg carries the burned shares in proportion to its value at the burn price, to be
+    // restored along with its amount if it fails.
+    let value0 = Uint512::from(amount0) * Uint512::from(pool_info.amount1 + amount1);
+    let value1 = Uint512::from(amount1) * Uint512::from(pool_info.amount0 + amount0);
+    let liquidity0 = if (value0 + value1).is_zero() {
+        amount / Uint256::from(2u8)
+    } else {
+        Uint256::try_from(Uint512::from(amount) * value0 / (value0 + value1))
+            .map_err(StdError::from)?
+    };
+    Ok(Response::new()
+        .add_messages(payout(
+            deps.storage,
+            &env,
+            PayoutKind::Withdrawal {
+                holder: holder.clone(),
+                liquidity: liquidity0,
+            },
+            pool_info.meta.chain0_id,
+            pool_id,
+            amount0,
+            &receiver0,
+        )?)
+        .add_messages(payout(
+            deps.storage,
+            &env,
+            PayoutKind::Withdrawal {
+                holder,
+                liquidity: amount - liquidity0,
+            },
+            pool_info.meta.chain1_id,
+            pool_id,
+            amount1,
+            &receiver1,
+        )?))
+}
+
+/// Burn LP shares and pay out only on `chain_id`, swapping the other leg through the pool.
+#[allow(clippy::too_many_arguments)]
+fn remove_liquidity_single(
+    deps: DepsMut,
+    env: Env,
+    holder: Addr,
+    pool_id: Uint256,
+    amount: Uint256,
+    chain_id: Uint256,
+    receiver: String,
+    min_amount_out: Uint256,
+    deadline: u64,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    let (mut pool_info, amount0, amount1, remaining) =
+        burn_liquidity(deps.as_ref(), &env, &holder, pool_id, amount, deadline)?;
+    let amount_out = if chain_id == pool_info.meta.chain0_id {
+        let swapped = get_amount_out(&pool_info, false, amount1)?;
+        pool_info.amount1 += amount1;
+        pool_info.amount0 -= swapped;
+        amount0 + swapped
+    } else if chain_id == pool_info.meta.chain1_id {
+        let swapped = get_amount_out(&pool_info, true, amount0)?;
+        pool_info.amount0 += amount0;
+        pool_info.amount1 -= swapped;
+        amount1 + swapped
+    } else {
+        return Err(ChainNotInPool { pool_id, chain_id });
+    };
+    if amount_out < min_amount_out {
+        return Err(SlippageExceeded {
+            amount: amount_out,
+            limit: min_amount_out,
+        });
+    }
+    let receiver = chain_encoder(deps.storage, chain_id)?.canonical_address(&receiver)?;
+    save_burn(deps.storage, &holder, &pool_info, remaining)?;
+
+    Ok(Response::new().add_messages(payout(
         deps.storage,
-        (pool_id_key, info.sender.as_bytes()),
-        |liquidity| -> StdResult<_> {
-            Ok(liquidity.unwrap_or_default().checked_sub(amount).unwrap())
+        &env,
+        PayoutKind::Withdrawal {
+            holder,
+            liquidity: amount,
         },
-    )?;
-    #[allow(deprecated)]
-    let contract = Contract {
-        constructor: None,
-        functions: BTreeMap::from_iter(vec![(
-            "remove_liquidity".to_string(),
-            vec![Function {
-                name: "remove_liquidity".to_string(),
-                inputs: vec![
-                    Param {
-                        name: "pool_id".to_string(),
-                        kind: ParamType::Uint(256),
-                        internal_type: None,
-                    },
-                    Param {
-                        name: "amount".to_string(),
-                        kind: ParamType::Uint(256),
-                        internal_type: None,
-                    },
-                    Param {
-                        name: "recipient".to_string(),
-                        kind: ParamType::Address,
-                        internal_type: None,
-                    },
-                ],
-                outputs: Vec::new(),
-                constant: None,
-                state_mutability: StateMutability::NonPayable,
-            }],
-        )]),
-        events: BTreeMap::new(),
-        errors: BTreeMap::new(),
-        receive: false,
-        fallback: false,
-    };
+        chain_id,
+        pool_id,
+        amount_out,
+        &receiver,
+    )?))
+}
 
-    Ok(Response::new()
-        .add_message(CosmosMsg::Custom(PalomaMsg {
-            job_id: POOL_FACTORIES.load(deps.storage, &chain0_id.to_be_bytes())?,
-            payload: Binary(
-                contract
-                    .function("remove_liquidity")
-                    .unwrap()
-                    .encode_input(&[
-                        Token::Uint(Uint::from_str(chain0_id.to_string().as_str()).unwrap()),
-                        Token::Uint(Uint::from_str(amount0.to_string().as_str()).unwrap()),
-                        Token::Address(Address::from_str(receiver0.as_str()).unwrap()),
-                    ])
-                    .unwrap(),
-            ),
-        }))
-        .add_message(CosmosMsg::Custom(PalomaMsg {
-            job_id: POOL_FACTORIES.load(deps.storage, &chain1_id.to_be_bytes())?,
-            payload: Binary(
-                contract
-                    .function("remove_liquidity")
-                    .unwrap()
-                    .encode_input(&[
-                        Token::Uint(Uint::from_str(chain1_id.to_string().as_str()).unwrap()),
-                        Token::Uint(Uint::from_str(amount1.to_string().as_str()).unwrap()),
-                        Token::Address(Address::from_str(receiver1.as_str()).unwrap()),
-                    ])
-                    .unwrap(),
+/// Query data from this contract. See QueryMsg submessages for details.
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Chain { chain_id } => to_binary(&query_chain(deps, chain_id)?),
+        QueryMsg::Chains { start_after, limit } => {
+            to_binary(&query_chains(deps, start_after, limit)?)
+        }
+        QueryMsg::Token { chain_id, token } => to_binary(&query_token(deps, chain_id, token)?),
+        QueryMsg::Tokens { start_after, limit } => {
+            to_binary(&query_tokens(deps, start_after, limit)?)
+        }
+        QueryMsg::AllowedTokens {
+            chain_id,
+            start_after,
+            limit,
+        } => to_binary(&query_allowed_tokens(deps, chain_id, start_after, limit)?),
+        QueryMsg::PoolCreationFee {} => {
+            to_binary(&POOL_CREATION_FEE.may_load(deps.storage)?.flatten())
+        }
+        QueryMsg::Pool { pool_id } => {
+            to_binary(&pools_info().load(deps.storage, pool_id.to_be_bytes().as_slice())?)
+        }
+        QueryMsg::LiquidityInvariant { pool_id } => {
+            to_binary(&query_liquidity_invariant(deps, pool_id)?)
+        }
+        QueryMsg::Liquidity { pool_id, owner } => {
+            let holder =
+                lp_holder(deps, owner).map_err(|err| StdError::generic_err(err.to_string()))?;
+            to_binary(
+                &LIQUIDITY
+                    .may_load(
+                        deps.storage,
+                        (pool_id.to_be_bytes().as_slice(), holder.as_bytes()),
+                    )?
+                    .unwrap_or_default(),
+            )
+        }
+        QueryMsg::SignerNonce { owner } => {
+            let holder =
+                lp_holder(deps, owner).map_err(|err| StdError::generic_err(err.to_string()))?;
+            to_binary(
+                &SIGNER_NONCES
+                    .may_load(deps.storage, holder.as_str())?
+                    .unwrap_or_default(),
+            )
+        }
+        QueryMsg::LiquidityQueue {
+            pool_id,
+            start_after,
+            limit,
+        } => to_binary(&query_liquidity_queue(deps, pool_id, start_after, limit)?),
+        QueryMsg::Matching { pool_id } => {
+            to_binary(&MATCHING.may_load(deps.storage, pool_id.to_be_bytes().as_slice())?)
+        }
+        QueryMsg::SwapBatch { pool_id } => {
+            to_binary(&SWAP_BATCHES.may_load(deps.storage, pool_id.to_be_bytes().as_slice())?)
+        }
+        QueryMsg::SwapQueue {
+            pool_id,
+            start_after,
+            limit,
+        } => to_binary(&query_swap_queue(deps, pool_id, start_after, limit)?),
+        QueryMsg::QueuedSwap { pool_id, seq } => to_binary(&query_queued_swap(deps, pool_id, seq)?),
+        QueryMsg::Relayer {} => to_binary(&RELAYER.may_load(deps.storage)?.flatten()),
+        QueryMsg::PendingSettlements { start_after, limit } => {
+            to_binary(&query_pending_settlements(deps, start_after, limit)?)
+        }
+        QueryMsg::DepositProcessed {
+            chain_id,
+            tx_hash,
+            log_index,
+        } => to_binary(&PROCESSED_DEPOSITS.has(
+            deps.storage,
+            (
+                chain_id.to_be_bytes().as_slice(),
+                &tx_hash.to_lowercase(),
+                log_index,
             ),
-        })))
+        )),
+        QueryMsg::Outbox { chain_id, pool_id } => to_binary(
+            &OUTBOX
+                .may_load(
+                    deps.storage,
+                    (
+                        chain_id.to_be_bytes().as_slice(),
+                        pool_id.to_be_bytes().as_slice(),
+                    ),
+                )?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::Settlement { nonce } => {
+            to_binary(&PENDING_SETTLEMENTS.may_load(deps.storage, nonce)?)
+        }
+        QueryMsg::DepositIntent { intent_id } => {
+            to_binary(&DEPOSIT_INTENTS.load(deps.storage, intent_id)?)
+        }
+        QueryMsg::Quote {
+            chain_from_id,
+            chain_to_id,
+            token_from,
+            token_to,
+            amount,
+        } => to_binary(&query_quote(
+            deps,
+            chain_from_id,
+            chain_to_id,
+            token_from,
+            token_to,
+            amount,
+        )?),
+    }
 }
 
-/// Query data from this contract. Currently no query interface is provided.
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(_deps: Deps, _env: Env, _msg: QueryMsg) -> StdResult<Binary> {
-    unimplemented!()
+fn query_liquidity_queue(
+    deps: Deps,
+    pool_id: Uint256,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<LiquidityQueueResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let deposits = liquidity_queue()
+        .prefix(pool_id.to_be_bytes().as_slice())
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (seq, deposit) = item?;
+            Ok(QueuedDeposit { seq, deposit })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(LiquidityQueueResponse { pool_id, deposits })
+}
+
+fn query_swap_queue(
+    deps: Deps,
+    pool_id: Uint256,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<SwapQueueResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let binding = pool_id.to_be_bytes();
+    let queue = SWAP_QUEUE.prefix(binding.as_slice());
+    let depth = queue
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .count() as u64;
+    let swaps = queue
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (seq, swap) = item?;
+            Ok(SwapQueueEntry { seq, swap })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(SwapQueueResponse {
+        pool_id,
+        depth,
+        swaps,
+    })
+}
+
+fn query_queued_swap(deps: Deps, pool_id: Uint256, seq: u64) -> StdResult<QueuedSwapResponse> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let swap = SWAP_QUEUE.load(deps.storage, (pool_id_key, seq))?;
+    let queue = SWAP_QUEUE.prefix(pool_id_key);
+    let position = queue
+        .keys_raw(
+            deps.storage,
+            None,
+            Some(Bound::exclusive(seq)),
+            Order::Ascending,
+        )
+        .count() as u64;
+    let depth = queue
+        .keys_raw(deps.storage, None, None, Order::Ascending)
+        .count() as u64;
+    Ok(QueuedSwapResponse {
+        swap,
+        position,
+        depth,
+    })
+}
+
+fn query_pending_settlements(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingSettlementsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let settlements = PENDING_SETTLEMENTS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| Ok(item?.1))
+        .collect::<StdResult<_>>()?;
+    Ok(PendingSettlementsResponse { settlements })
+}
+
+fn query_chain(deps: Deps, chain_id: Uint256) -> StdResult<ChainResponse> {
+    let binding = chain_id.to_be_bytes();
+    let chain_key = binding.as_slice();
+    let chain_info = POOL_FACTORIES.load(deps.storage, chain_key)?;
+    Ok(ChainResponse {
+        chain_id,
+        factory: chain_info.factory,
+        encoding: chain_info.encoding,
+        require_checksum: chain_info.require_checksum,
+        batch_size: chain_info.batch_size,
+        nonce: CHAIN_NONCES
+            .may_load(deps.storage, chain_key)?
+            .unwrap_or_default(),
+    })
+}
+
+fn query_chains(
+    deps: Deps,
+    start_after: Option<Uint256>,
+    limit: Option<u32>,
+) -> StdResult<ChainsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let binding = start_after.map(|chain_id| chain_id.to_be_bytes());
+    let start = binding.as_ref().map(|key| Bound::exclusive(key.as_slice()));
+    let chains = POOL_FACTORIES
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, chain_info) = item?;
+            Ok(ChainResponse {
+                chain_id: uint256_from_key(&key),
+                factory: chain_info.factory,
+                encoding: chain_info.encoding,
+                require_checksum: chain_info.require_checksum,
+                batch_size: chain_info.batch_size,
+                nonce: CHAIN_NONCES
+                    .may_load(deps.storage, &key)?
+                    .unwrap_or_default(),
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(ChainsResponse { chains })
+}
+
+fn query_token(deps: Deps, chain_id: Uint256, token: String) -> StdResult<TokenResponse> {
+    let token = chain_encoder(deps.storage, chain_id)
+        .and_then(|encoder| encoder.canonical_token(&token))
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let token_info = TOKENS.load(deps.storage, (chain_id.to_be_bytes().as_slice(), &token))?;
+    Ok(TokenResponse {
+        chain_id,
+        token,
+        decimals: token_info.decimals,
+        symbol: token_info.symbol,
+    })
+}
+
+fn query_tokens(
+    deps: Deps,
+    start_after: Option<(Uint256, String)>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let binding = start_after.map(|(chain_id, token)| (chain_id.to_be_bytes(), token));
+    let start = binding
+        .as_ref()
+        .map(|(chain_id, token)| Bound::exclusive((chain_id.as_slice(), token.as_str())));
+    let tokens = TOKENS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let ((chain_id, token), token_info) = item?;
+            Ok(TokenResponse {
+                chain_id: uint256_from_key(&chain_id),
+                token,
+                decimals: token_info.decimals,
+                symbol: token_info.symbol,
+            })
+        })
+        .collect::<StdResult<_>>()?;
+    Ok(TokensResponse { tokens })
+}
+
+fn query_allowed_tokens(
+    deps: Deps,
+    chain_id: Uint256,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllowedTokensResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_deref().map(Bound::exclusive);
+    let tokens = ALLOWED_TOKENS
+        .prefix(chain_id.to_be_bytes().as_slice())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<_>>()?;
+    Ok(AllowedTokensResponse { chain_id, tokens })
+}
+
+fn query_liquidity_invariant(
+    deps: Deps,
+    pool_id: Uint256,
+) -> StdResult<LiquidityInvariantResponse> {
+    let binding = pool_id.to_be_bytes();
+    let pool_id_key = binding.as_slice();
+    let pool_info = pools_info().load(deps.storage, pool_id_key)?;
+    let mut holders_liquidity = Uint256::zero();
+    for liquidity in LIQUIDITY
+        .prefix(pool_id_key)
+        .range(deps.storage, None, None, Order::Ascending)
+    {
+        holders_liquidity += liquidity?.1;
+    }
+    Ok(LiquidityInvariantResponse {
+        pool_id,
+        total_liquidity: pool_info.total_liquidity,
+        holders_liquidity,
+        locked_liquidity: LIQUIDITY
+            .may_load(
+                deps.storage,
+                (pool_id_key, LOCKED_LIQUIDITY_HOLDER.as_bytes()),
+            )?
+            .unwrap_or_default(),
+        holds: pool_info.total_liquidity == holders_liquidity,
+    })
+}
+
+fn query_quote(
+    deps: Deps,
+    chain_from_id: Uint256,
+    chain_to_id: Uint256,
+    token_from: String,
+    token_to: String,
+    amount: Uint256,
+) -> StdResult<QuoteResponse> {
+    let pool_id = find_pool_id(deps, chain_from_id, chain_to_id, token_from, token_to)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let pool_info = pools_info().load(deps.storage, pool_id.to_be_bytes().as_slice())?;
+    let is_chain0 = pool_info.meta.chain0_id == chain_from_id;
+    let (reserve_in, reserve_out) = normalized_reserves(&pool_info, is_chain0);
+    let amount_out = get_amount_out(&pool_info, is_chain0, amount)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    Ok(QuoteResponse {
+        pool_id,
+        amount_out,
+        spot_price: Decimal256::from_ratio(reserve_out, reserve_in),
+    })
+}
+
+fn uint256_from_key(key: &[u8]) -> Uint256 {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(key);
+    Uint256::from_be_bytes(bytes)
 }
 
 #[cfg(test)]
@@ -0,0 +1,98 @@
+//! EIP-712 typed data signed by EVM accounts owning LP shares, and recovery of their signers.
+//!
+//! Signatures are bound to the source chain of the owner and to this contract through the domain,
+//! whose salt is the Keccak-256 hash of the contract address.
+
+use crate::msg::SignedAction;
+use crate::ContractError;
+use cosmwasm_std::{Api, Uint256};
+use sha3::{Digest, Keccak256};
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId,bytes32 salt)";
+const DOMAIN_NAME: &str = "Paloma Cross-Chain AMM";
+const DOMAIN_VERSION: &str = "1";
+const REMOVE_LIQUIDITY_TYPE: &str = "RemoveLiquidity(uint256 poolId,uint256 amount,string receiver0,string receiver1,uint256 minAmount0,uint256 minAmount1,uint256 nonce,uint256 deadline)";
+const CANCEL_QUEUED_DEPOSITS_TYPE: &str =
+    "CancelQueuedDeposits(uint256 poolId,uint256 nonce,uint256 deadline)";
+
+type Word = [u8; 32];
+
+fn keccak(data: &[u8]) -> Word {
+    Keccak256::digest(data).into()
+}
+
+fn hash_struct(words: &[Word]) -> Word {
+    keccak(&words.concat())
+}
+
+/// The digest an owner on `chain_id` signs to authorize `action`.
+pub fn digest(
+    contract: &str,
+    chain_id: Uint256,
+    action: &SignedAction,
+    nonce: u64,
+    deadline: u64,
+) -> Word {
+    let domain = hash_struct(&[
+        keccak(DOMAIN_TYPE.as_bytes()),
+        keccak(DOMAIN_NAME.as_bytes()),
+        keccak(DOMAIN_VERSION.as_bytes()),
+        chain_id.to_be_bytes(),
+        keccak(contract.as_bytes()),
+    ]);
+    let nonce = Uint256::from(nonce).to_be_bytes();
+    let deadline = Uint256::from(deadline).to_be_bytes();
+    let message = match action {
+        SignedAction::RemoveLiquidity {
+            pool_id,
+            amount,
+            receiver0,
+            receiver1,
+            min_amount0,
+            min_amount1,
+        } => hash_struct(&[
+            keccak(REMOVE_LIQUIDITY_TYPE.as_bytes()),
+            pool_id.to_be_bytes(),
+            amount.to_be_bytes(),
+            keccak(receiver0.as_bytes()),
+            keccak(receiver1.as_bytes()),
+            min_amount0.to_be_bytes(),
+            min_amount1.to_be_bytes(),
+            nonce,
+            deadline,
+        ]),
+        SignedAction::CancelQueuedDeposits { pool_id } => hash_struct(&[
+            keccak(CANCEL_QUEUED_DEPOSITS_TYPE.as_bytes()),
+            pool_id.to_be_bytes(),
+            nonce,
+            deadline,
+        ]),
+    };
+    keccak(&[b"\x19\x01".as_slice(), &domain, &message].concat())
+}
+
+/// Recover the address signing `digest` from a 65-byte `r || s || v` signature, with `v` either
+/// 0/1 or 27/28. Returns the address lowercase with a `0x` prefix.
+pub fn recover(api: &dyn Api, digest: &Word, signature: &[u8]) -> Result<String, ContractError> {
+    let (v, signature) = match signature {
+        [signature @ .., v] if signature.len() == 64 => (v, signature),
+        _ => return Err(ContractError::InvalidSignature {}),
+    };
+    let recovery_param = match v {
+        0 | 1 => *v,
+        27 | 28 => v - 27,
+        _ => return Err(ContractError::InvalidSignature {}),
+    };
+    let pubkey = api
+        .secp256k1_recover_pubkey(digest, signature, recovery_param)
+        .map_err(|_| ContractError::InvalidSignature {})?;
+    // Uncompressed public key, the address is the tail of the hash of its coordinates.
+    let hash = keccak(&pubkey[1..]);
+    Ok(format!(
+        "0x{}",
+        hash[12..]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    ))
+}
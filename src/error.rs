@@ -1,4 +1,4 @@
-use cosmwasm_std::{StdError, Uint256};
+use cosmwasm_std::{Addr, Coin, StdError, Timestamp, Uint256};
 use thiserror::Error;
 
 /// Custom errors that can be thrown from our contract.
@@ -20,4 +20,285 @@ pub enum ContractError {
         /// The target chain token.
         token1: String,
     },
+
+    /// Attempted to refund the bootstrap of a pool which has liquidity.
+    #[error("Pool already bootstrapped pool_id - {pool_id:?}")]
+    PoolBootstrapped {
+        /// The bootstrapped pool.
+        pool_id: Uint256,
+    },
+
+    /// Attempted to refund the bootstrap of a pool before its deadline.
+    #[error("Pool bootstrap not expired pool_id - {pool_id:?}, deadline - {deadline}")]
+    BootstrapNotExpired {
+        /// The pool being bootstrapped.
+        pool_id: Uint256,
+        /// Time after which the bootstrap can be refunded.
+        deadline: Timestamp,
+    },
+
+    /// The sender is not allowed to perform this action.
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    /// Attempted to register a chain which is already registered.
+    #[error("Chain already registered chain_id - {chain_id:?}")]
+    ChainExists {
+        /// The registered chain.
+        chain_id: Uint256,
+    },
+
+    /// Referenced a chain which has not been registered.
+    #[error("Chain not registered chain_id - {chain_id:?}")]
+    ChainNotFound {
+        /// The unknown chain.
+        chain_id: Uint256,
+    },
+
+    /// Referenced a chain which is not one of the pool's chains.
+    #[error("Chain not in pool pool_id - {pool_id:?}, chain_id - {chain_id:?}")]
+    ChainNotInPool {
+        /// The pool.
+        pool_id: Uint256,
+        /// The chain which is not part of the pool.
+        chain_id: Uint256,
+    },
+
+    /// Attempted to deregister a chain which still has pools.
+    #[error("Chain in use by pool chain_id - {chain_id:?}, pool_id - {pool_id:?}")]
+    ChainInUse {
+        /// The chain being deregistered.
+        chain_id: Uint256,
+        /// A pool still using the chain.
+        pool_id: Uint256,
+    },
+
+    /// Referenced a token which has not been registered.
+    #[error("Token not registered chain_id - {chain_id:?}, token - {token:?}")]
+    TokenNotRegistered {
+        /// The token chain.
+        chain_id: Uint256,
+        /// The unknown token.
+        token: String,
+    },
+
+    /// Attempted to create a pool with a token which is not listed.
+    #[error("Token not allowed chain_id - {chain_id:?}, token - {token:?}")]
+    TokenNotAllowed {
+        /// The token chain.
+        chain_id: Uint256,
+        /// The unlisted token.
+        token: String,
+    },
+
+    /// Attempted to create a permissionless pool without paying the creation fee.
+    #[error("Insufficient pool creation fee - {fee}")]
+    InsufficientCreationFee {
+        /// The required fee.
+        fee: Coin,
+    },
+
+    /// Attempted to register a token with unsupported decimals.
+    #[error("Invalid token decimals - {decimals:?}")]
+    InvalidDecimals {
+        /// The rejected decimals.
+        decimals: u8,
+    },
+
+    /// Attempted to trade against a pool without reserves.
+    #[error("Pool has no liquidity pool_id - {pool_id:?}")]
+    EmptyPool {
+        /// The empty pool.
+        pool_id: Uint256,
+    },
+
+    /// The first deposit into a pool does not cover the locked minimum liquidity, or a zap would
+    /// mint none.
+    #[error("Insufficient liquidity minted - {liquidity:?}")]
+    InsufficientLiquidityMinted {
+        /// The liquidity the deposit would mint.
+        liquidity: Uint256,
+    },
+
+    /// Attempted to burn more LP shares than the sender holds.
+    #[error("Insufficient liquidity available - {available:?}, requested - {requested:?}")]
+    InsufficientLiquidity {
+        /// LP shares held by the sender.
+        available: Uint256,
+        /// LP shares requested to burn.
+        requested: Uint256,
+    },
+
+    /// The request arrived after its deadline.
+    #[error("Expired deadline - {deadline}")]
+    Expired {
+        /// Unix time in seconds of the deadline.
+        deadline: u64,
+    },
+
+    /// Referenced a deposit intent which does not exist or was settled.
+    #[error("Deposit intent not found intent_id - {intent_id}")]
+    IntentNotFound {
+        /// The unknown intent.
+        intent_id: u64,
+    },
+
+    /// Attempted to refund a deposit intent before its expiry.
+    #[error("Deposit intent not expired intent_id - {intent_id}, expiry - {expiry}")]
+    IntentNotExpired {
+        /// The pending intent.
+        intent_id: u64,
+        /// Time after which the intent can be refunded.
+        expiry: Timestamp,
+    },
+
+    /// The resulting amount is worse than the caller's limit.
+    #[error("Slippage exceeded amount - {amount:?}, limit - {limit:?}")]
+    SlippageExceeded {
+        /// The amount the trade would produce.
+        amount: Uint256,
+        /// The caller's limit.
+        limit: Uint256,
+    },
+
+    /// A round-robin matching policy needs a positive cap.
+    #[error("Invalid matching policy")]
+    InvalidMatchingPolicy {},
+
+    /// A deposit too small to match any queued liquidity.
+    #[error("Deposit too small to match queued liquidity amount - {amount:?}")]
+    DepositTooSmall {
+        /// The deposited amount.
+        amount: Uint256,
+    },
+
+    /// The owner has no deposits queued in the pool.
+    #[error("No queued deposits pool_id - {pool_id:?}, owner - {owner}")]
+    QueuedDepositNotFound {
+        /// The pool searched.
+        pool_id: Uint256,
+        /// The owner of the deposits.
+        owner: Addr,
+    },
+
+    /// No deposit is part-way through matching the liquidity queue of the pool.
+    #[error("No matching in progress pool_id - {pool_id:?}")]
+    NoMatchingInProgress {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+
+    /// The batch auction window of the pool has not passed yet.
+    #[error("Batch not closed pool_id - {pool_id:?}, closes - {closes}")]
+    BatchNotClosed {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Time after which the batch can be settled.
+        closes: Timestamp,
+    },
+
+    /// The pool has no open batch auction.
+    #[error("Batch not found pool_id - {pool_id:?}")]
+    BatchNotFound {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+
+    /// Exact-output swaps cannot be cleared in a batch auction.
+    #[error("Exact-output swap in batch mode pool_id - {pool_id:?}")]
+    ExactOutInBatch {
+        /// The pool in batch mode.
+        pool_id: Uint256,
+    },
+
+    /// No pending payout has the nonce.
+    #[error("Settlement not found nonce - {nonce}")]
+    SettlementNotFound {
+        /// The unknown nonce.
+        nonce: u64,
+    },
+
+    /// The relayed deposit was already processed.
+    #[error(
+        "Duplicate deposit chain_id - {chain_id}, tx_hash - {tx_hash}, log_index - {log_index}"
+    )]
+    DuplicateDeposit {
+        /// Source chain id.
+        chain_id: Uint256,
+        /// Hash of the source chain transaction.
+        tx_hash: String,
+        /// Index of the deposit event within the transaction.
+        log_index: u64,
+    },
+
+    /// The address is not a valid account on its chain.
+    #[error("Invalid address - {address}")]
+    InvalidAddress {
+        /// The rejected address.
+        address: String,
+    },
+
+    /// The token is not valid on its chain.
+    #[error("Invalid token - {token}")]
+    InvalidToken {
+        /// The rejected token.
+        token: String,
+    },
+
+    /// The zero address was given as a token or account.
+    #[error("Zero address")]
+    ZeroAddress {},
+
+    /// The mixed-case address does not match its EIP-55 checksum, or a checksum is required.
+    #[error("Invalid address checksum - {address}")]
+    InvalidChecksum {
+        /// The rejected address.
+        address: String,
+    },
+
+    /// The signature is malformed or does not recover a signer.
+    #[error("Invalid signature")]
+    InvalidSignature {},
+
+    /// The signed intent does not carry the next nonce of its signer.
+    #[error("Invalid nonce - {nonce}, expected - {expected}")]
+    InvalidNonce {
+        /// Nonce of the intent.
+        nonce: u64,
+        /// Next nonce of the signer.
+        expected: u64,
+    },
+
+    /// Signed intents are only accepted from owners on EVM chains.
+    #[error("Signing unsupported chain_id - {chain_id:?}")]
+    SigningUnsupported {
+        /// The chain of the owner.
+        chain_id: Uint256,
+    },
+
+    /// No payouts were collected for the pool on the chain.
+    #[error("Outbox empty chain_id - {chain_id:?}, pool_id - {pool_id:?}")]
+    OutboxEmpty {
+        /// The chain ID.
+        chain_id: Uint256,
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+
+    /// Funds were attached which are not exactly the fee due.
+    #[error("Unexpected funds")]
+    UnexpectedFunds {},
+
+    /// Attempted to deposit or transfer nothing.
+    #[error("Zero amount")]
+    ZeroAmount {},
+
+    /// A pool's chains must be given in ascending order of their IDs.
+    #[error("Unordered chains chain0_id - {chain0_id:?}, chain1_id - {chain1_id:?}")]
+    UnorderedChains {
+        /// The first chain ID.
+        chain0_id: Uint256,
+        /// The second chain ID.
+        chain1_id: Uint256,
+    },
 }
@@ -2,7 +2,9 @@
 
 #![warn(missing_docs)]
 
+pub mod abi;
 pub mod contract;
+pub mod eip712;
 mod error;
 pub mod msg;
 pub mod state;
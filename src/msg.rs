@@ -1,12 +1,17 @@
 //! Messages used to instantiate/execute/query the contract.
 
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Binary, CustomMsg, Uint256};
+use cosmwasm_std::{Addr, Binary, Coin, CustomMsg, Decimal256, Uint256};
+
+use crate::state::{
+    DepositIntent, Encoding, LiquidityQueueElement, LpOwner, MatchingPolicy, PoolInfo, QueuedSwap,
+    Settlement, SwapBatch, SwapMode,
+};
 
 /// Arguments to instantiate our contract.
 #[cw_serde]
 pub struct InstantiateMsg {
-    /// Deadline for when the pool first has liquidity.
+    /// Default seconds for a pool to first have liquidity before it can be recreated.
     pub deadline: u64,
 }
 
@@ -19,10 +24,66 @@ pub enum ExecuteMsg {
         chain_id: Uint256,
         /// The factory contract we will use to mint tokens.
         factory: String,
+        /// How payloads for the factory are encoded, EVM if unset.
+        encoding: Option<Encoding>,
+        /// Require EIP-55 checksummed addresses on an EVM chain, false if unset.
+        require_checksum: Option<bool>,
+    },
+    /// Replace the factory job of a registered chain.
+    UpdateChain {
+        /// The chain ID.
+        chain_id: Uint256,
+        /// The new factory contract job.
+        factory: String,
+    },
+    /// Collect payouts on a chain per pool and send them in batches of `batch_size`, or send each
+    /// payout on its own with `None`.
+    SetBatchSize {
+        /// The chain ID.
+        chain_id: Uint256,
+        /// Payouts per batch.
+        batch_size: Option<u32>,
+    },
+    /// Remove a registered chain which has no pools.
+    DeregisterChain {
+        /// The chain ID.
+        chain_id: Uint256,
+    },
+    /// Register or update the metadata of a token.
+    RegisterToken {
+        /// The token chain ID.
+        chain_id: Uint256,
+        /// The token address.
+        token: String,
+        /// Number of decimals of the token.
+        decimals: u8,
+        /// Display symbol of the token.
+        symbol: String,
+    },
+    /// List a registered token for pool creation.
+    AllowToken {
+        /// The token chain ID.
+        chain_id: Uint256,
+        /// The token address.
+        token: String,
+    },
+    /// Remove a token from the pool creation list.
+    DisallowToken {
+        /// The token chain ID.
+        chain_id: Uint256,
+        /// The token address.
+        token: String,
+    },
+    /// Allow anyone to create pools with unlisted, registered tokens for a fee, or disable it with
+    /// `None`.
+    SetPoolCreationFee {
+        /// The fee paid to the admin, which can't be zero.
+        fee: Option<Coin>,
     },
-    /// Instantiate a new pool.
+    /// Instantiate a new pool. Tokens must be listed unless exactly the creation fee is attached.
+    /// Either way, tokens must have been registered by the admin.
     CreatePool {
-        /// Source chain id.
+        /// Source chain id, lower than `chain1_id`.
         chain0_id: Uint256,
         /// Target chain id.
         chain1_id: Uint256,
@@ -34,8 +95,20 @@ pub enum ExecuteMsg {
         chain0_init_depositor: String,
         /// Target chain depositor.
         chain1_init_depositor: String,
+        /// Seconds for the pool to first have liquidity, defaulting to the instantiated deadline.
+        bootstrap_deadline: Option<u64>,
+        /// How counter-side deposits are matched against queued ones, defaulting to FIFO.
+        matching_policy: Option<MatchingPolicy>,
+        /// Seconds to collect swaps into a batch auction cleared at one price, or `None` to swap
+        /// immediately.
+        batch_window: Option<u64>,
+    },
+    /// Refund the queued deposits of a pool which missed its bootstrap deadline.
+    RefundBootstrap {
+        /// The pool ID.
+        pool_id: Uint256,
     },
-    /// Initiate a swap.
+    /// Initiate a swap. Only callable by the relayer.
     Swap {
         /// Source chain id.
         chain_from_id: Uint256,
@@ -51,8 +124,52 @@ pub enum ExecuteMsg {
         receiver: String,
         /// Amount to transfer.
         amount: Uint256,
+        /// Whether `amount` is swapped in full or only as much as needed for a fixed output.
+        mode: SwapMode,
+        /// Unix time in seconds until which the swap waits for liquidity instead of failing when
+        /// the reserves cannot fill it, including while they are tied up in pending withdrawals or
+        /// the pool is not bootstrapped yet. A missed slippage limit still fails. The queue
+        /// position is returned as response data. Ignored in batch mode.
+        queue_expiry: Option<u64>,
+        /// Hash of the source chain transaction making the deposit.
+        tx_hash: String,
+        /// Index of the deposit event within the transaction.
+        log_index: u64,
+    },
+    /// Fill queued swaps of a pool in order as far as liquidity allows, refunding expired ones.
+    /// Anyone may call this. Also run after every deposit and failed payout.
+    ProcessSwapQueue {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Set the relayer allowed to acknowledge payouts, or remove it with `None`.
+    SetRelayer {
+        /// The relayer address.
+        relayer: Option<Addr>,
     },
-    /// Add funds to a pool.
+    /// Finalize a payout delivered on its target chain. Only callable by the relayer.
+    AckSettlement {
+        /// The payout nonce.
+        nonce: u64,
+    },
+    /// Roll back a payout which failed on its target chain. Only callable by the relayer.
+    FailSettlement {
+        /// The payout nonce.
+        nonce: u64,
+    },
+    /// Send the payouts collected for a pool on a chain as one batch. Only callable by the relayer.
+    FlushOutbox {
+        /// The chain ID.
+        chain_id: Uint256,
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Clear the batch auction of a pool once its window has passed. Anyone may call this.
+    SettleBatch {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Add funds to a pool. Only callable by the relayer.
     AddLiquidity {
         /// Pool to add liquidity to.
         pool_id: Uint256,
@@ -64,25 +181,156 @@ pub enum ExecuteMsg {
         amount: Uint256,
         /// Address sending funds.
         sender: String,
-        /// Address receiving funds.
-        receiver: Addr,
+        /// Owner receiving the LP shares.
+        receiver: LpOwner,
+        /// Swap part of the deposit and mint LP shares immediately instead of queueing it.
+        zap: Option<ZapIn>,
+        /// Deposit intent this deposit is one leg of. The LP shares go to the intent owner
+        /// instead of `receiver`, and `zap` is ignored. A leg which doesn't match the intent is
+        /// refunded.
+        intent_id: Option<u64>,
+        /// Hash of the source chain transaction making the deposit.
+        tx_hash: String,
+        /// Index of the deposit event within the transaction.
+        log_index: u64,
+    },
+    /// Match the next batch of queued deposits against a deposit left part-way through matching,
+    /// or else take up the next deposit which arrived meanwhile. Anyone may call this.
+    ContinueMatching {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Remove the sender's queued deposits from a pool, refunding them to their senders.
+    CancelQueuedDeposits {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Register a double-sided deposit, so its two legs are minted together instead of being
+    /// matched against the liquidity queue. The intent id is returned as response data.
+    RegisterDepositIntent {
+        /// Pool to deposit into.
+        pool_id: Uint256,
+        /// Amount to be deposited on the source chain.
+        amount0: Uint256,
+        /// Amount to be deposited on the target chain.
+        amount1: Uint256,
+        /// Address depositing on the source chain.
+        sender0: String,
+        /// Address depositing on the target chain.
+        sender1: String,
+        /// Maximum price movement, in basis points, between registration and the second leg.
+        tolerance_bps: u16,
+        /// Unix time in seconds after which arrived legs are refunded.
+        expiry: u64,
+    },
+    /// Refund the arrived legs of an expired deposit intent.
+    RefundDepositIntent {
+        /// The intent ID.
+        intent_id: u64,
     },
-    /// Transfer funds between cross chain accounts.
+    /// Burn LP shares of the sender and pay out both pool tokens.
     RemoveLiquidity {
-        /// Chain to transfer from.
-        chain0_id: Uint256,
-        /// Chain to transfer to.
-        chain1_id: Uint256,
-        /// Token to transfor from.
-        token0: String,
-        /// Token to transfer to.
-        token1: String,
+        /// Pool to remove liquidity from.
+        pool_id: Uint256,
+        /// LP shares to burn.
+        amount: Uint256,
         /// Receiver0 address.
         receiver0: String,
         /// Receiver1 address.
         receiver1: String,
-        /// Amount to transfer.
+        /// Minimum amount of token0 to receive.
+        min_amount0: Uint256,
+        /// Minimum amount of token1 to receive.
+        min_amount1: Uint256,
+        /// Unix time in seconds after which the removal is rejected.
+        deadline: u64,
+    },
+    /// Burn LP shares of an owner on a remote chain, as requested on that chain. Only callable by
+    /// the relayer.
+    RelayedRemoveLiquidity {
+        /// Chain the request was made on.
+        chain_id: Uint256,
+        /// Owner of the LP shares on `chain_id`, who made the request.
+        sender: String,
+        /// Hash of the source chain transaction making the request.
+        tx_hash: String,
+        /// Index of the request event within the transaction.
+        log_index: u64,
+        /// Pool to remove liquidity from.
+        pool_id: Uint256,
+        /// LP shares to burn.
+        amount: Uint256,
+        /// Receiver0 address.
+        receiver0: String,
+        /// Receiver1 address.
+        receiver1: String,
+        /// Minimum amount of token0 to receive.
+        min_amount0: Uint256,
+        /// Minimum amount of token1 to receive.
+        min_amount1: Uint256,
+        /// Unix time in seconds after which the removal is rejected.
+        deadline: u64,
+    },
+    /// Perform an action on LP shares owned on an EVM chain, signed by the owner as EIP-712 typed
+    /// data. Callable by anyone.
+    SubmitSignedIntent {
+        /// Chain of the owner.
+        chain_id: Uint256,
+        /// The signed action.
+        action: SignedAction,
+        /// Nonce of the intent among those signed by the owner.
+        nonce: u64,
+        /// Unix time in seconds after which the intent is rejected.
+        deadline: u64,
+        /// The 65-byte `r || s || v` signature.
+        signature: Binary,
+    },
+    /// Burn LP shares of the sender and pay out a single pool token, swapping the other leg.
+    RemoveLiquiditySingle {
+        /// Pool to remove liquidity from.
+        pool_id: Uint256,
+        /// LP shares to burn.
+        amount: Uint256,
+        /// Chain to receive the pool token on.
+        chain_id: Uint256,
+        /// Receiver address on `chain_id`.
+        receiver: String,
+        /// Minimum amount to receive, including the swapped leg.
+        min_amount_out: Uint256,
+        /// Unix time in seconds after which the removal is rejected.
+        deadline: u64,
+    },
+}
+
+/// Options for depositing a single token without waiting in the liquidity queue.
+#[cw_serde]
+pub struct ZapIn {
+    /// Minimum LP shares to mint, bounding the slippage of the internal swap.
+    pub min_liquidity: Uint256,
+}
+
+/// An action a remote owner can sign for anyone to submit.
+#[cw_serde]
+pub enum SignedAction {
+    /// Burn LP shares of the owner and pay out both pool tokens.
+    RemoveLiquidity {
+        /// Pool to remove liquidity from.
+        pool_id: Uint256,
+        /// LP shares to burn.
         amount: Uint256,
+        /// Receiver0 address.
+        receiver0: String,
+        /// Receiver1 address.
+        receiver1: String,
+        /// Minimum amount of token0 to receive.
+        min_amount0: Uint256,
+        /// Minimum amount of token1 to receive.
+        min_amount1: Uint256,
+    },
+    /// Withdraw all queued deposits of the owner into a pool.
+    CancelQueuedDeposits {
+        /// The pool ID.
+        pool_id: Uint256,
     },
 }
 
@@ -95,9 +343,309 @@ pub struct PalomaMsg {
     pub payload: Binary,
 }
 
-/// Currently cc-amm provides no queries.
+/// Queries exposed by cc-amm.
 #[cw_serde]
 #[derive(QueryResponses)]
-pub enum QueryMsg {}
+pub enum QueryMsg {
+    /// Look up the factory job of a registered chain.
+    #[returns(ChainResponse)]
+    Chain {
+        /// The chain ID.
+        chain_id: Uint256,
+    },
+    /// List registered chains, ordered by chain ID.
+    #[returns(ChainsResponse)]
+    Chains {
+        /// Only return chains after this ID.
+        start_after: Option<Uint256>,
+        /// Maximum number of chains to return.
+        limit: Option<u32>,
+    },
+    /// Look up the metadata of a registered token.
+    #[returns(TokenResponse)]
+    Token {
+        /// The token chain ID.
+        chain_id: Uint256,
+        /// The token address.
+        token: String,
+    },
+    /// List registered tokens, ordered by chain ID and address.
+    #[returns(TokensResponse)]
+    Tokens {
+        /// Only return tokens after this `(chain_id, token)` pair.
+        start_after: Option<(Uint256, String)>,
+        /// Maximum number of tokens to return.
+        limit: Option<u32>,
+    },
+    /// List the tokens of a chain which may be used for pool creation.
+    #[returns(AllowedTokensResponse)]
+    AllowedTokens {
+        /// The token chain ID.
+        chain_id: Uint256,
+        /// Only return tokens after this address.
+        start_after: Option<String>,
+        /// Maximum number of tokens to return.
+        limit: Option<u32>,
+    },
+    /// The fee for creating pools with unlisted tokens, if permissionless creation is enabled.
+    #[returns(Option<Coin>)]
+    PoolCreationFee {},
+    /// Get the LP shares of an owner in a pool.
+    #[returns(Uint256)]
+    Liquidity {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// The LP owner.
+        owner: LpOwner,
+    },
+    /// Get the nonce of the next intent signed by an owner.
+    #[returns(u64)]
+    SignerNonce {
+        /// The LP owner.
+        owner: LpOwner,
+    },
+    /// Look up a pool.
+    #[returns(PoolInfo)]
+    Pool {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Check that the LP shares of a pool add up to its total liquidity.
+    #[returns(LiquidityInvariantResponse)]
+    LiquidityInvariant {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// List the queued deposits of a pool in arrival order.
+    #[returns(LiquidityQueueResponse)]
+    LiquidityQueue {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Sequence number to start after.
+        start_after: Option<u64>,
+        /// Maximum number of deposits to return.
+        limit: Option<u32>,
+    },
+    /// Look up the deposit part-way through matching the liquidity queue of a pool, if any.
+    #[returns(Option<LiquidityQueueElement>)]
+    Matching {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Look up the open batch auction of a pool, if any.
+    #[returns(Option<SwapBatch>)]
+    SwapBatch {
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// List the queued swaps of a pool in arrival order.
+    #[returns(SwapQueueResponse)]
+    SwapQueue {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Sequence number to start after.
+        start_after: Option<u64>,
+        /// Maximum number of swaps to return.
+        limit: Option<u32>,
+    },
+    /// Look up a queued swap and its position in the queue.
+    #[returns(QueuedSwapResponse)]
+    QueuedSwap {
+        /// The pool ID.
+        pool_id: Uint256,
+        /// Sequence number returned when the swap was queued.
+        seq: u64,
+    },
+    /// Get the relayer allowed to acknowledge payouts.
+    #[returns(Option<Addr>)]
+    Relayer {},
+    /// List payouts awaiting acknowledgement.
+    #[returns(PendingSettlementsResponse)]
+    PendingSettlements {
+        /// Nonce to start after.
+        start_after: Option<u64>,
+        /// Maximum number of payouts to return.
+        limit: Option<u32>,
+    },
+    /// Check whether a relayed deposit was already processed.
+    #[returns(bool)]
+    DepositProcessed {
+        /// Source chain id.
+        chain_id: Uint256,
+        /// Hash of the source chain transaction.
+        tx_hash: String,
+        /// Index of the deposit event within the transaction.
+        log_index: u64,
+    },
+    /// List payouts collected for a pool on a chain and not yet sent.
+    #[returns(Vec<Settlement>)]
+    Outbox {
+        /// The chain ID.
+        chain_id: Uint256,
+        /// The pool ID.
+        pool_id: Uint256,
+    },
+    /// Look up a pending payout.
+    #[returns(Option<Settlement>)]
+    Settlement {
+        /// The payout nonce.
+        nonce: u64,
+    },
+    /// Look up a pending deposit intent.
+    #[returns(DepositIntent)]
+    DepositIntent {
+        /// The intent ID.
+        intent_id: u64,
+    },
+    /// Simulate a swap.
+    #[returns(QuoteResponse)]
+    Quote {
+        /// Source chain id.
+        chain_from_id: Uint256,
+        /// Target chain id.
+        chain_to_id: Uint256,
+        /// Source chain token.
+        token_from: String,
+        /// Target chain token.
+        token_to: String,
+        /// Amount to swap.
+        amount: Uint256,
+    },
+}
+
+/// A registered chain and its factory job.
+#[cw_serde]
+pub struct ChainResponse {
+    /// The chain ID.
+    pub chain_id: Uint256,
+    /// The factory contract job.
+    pub factory: String,
+    /// How payloads for the factory are encoded.
+    pub encoding: Encoding,
+    /// Whether EIP-55 checksummed addresses are required.
+    pub require_checksum: bool,
+    /// Payouts per batch, if payouts are batched.
+    pub batch_size: Option<u32>,
+    /// Nonce of the next payload sent to the factory.
+    pub nonce: u64,
+}
+
+/// A registered token and its metadata.
+#[cw_serde]
+pub struct TokenResponse {
+    /// The token chain ID.
+    pub chain_id: Uint256,
+    /// The token address.
+    pub token: String,
+    /// Number of decimals of the token.
+    pub decimals: u8,
+    /// Display symbol of the token.
+    pub symbol: String,
+}
+
+/// A page of registered tokens.
+#[cw_serde]
+pub struct TokensResponse {
+    /// The registered tokens.
+    pub tokens: Vec<TokenResponse>,
+}
+
+/// A page of tokens listed on a chain.
+#[cw_serde]
+pub struct AllowedTokensResponse {
+    /// The token chain ID.
+    pub chain_id: Uint256,
+    /// The listed token addresses.
+    pub tokens: Vec<String>,
+}
+
+/// The LP share accounting of a pool.
+#[cw_serde]
+pub struct LiquidityInvariantResponse {
+    /// The pool ID.
+    pub pool_id: Uint256,
+    /// Total liquidity recorded on the pool.
+    pub total_liquidity: Uint256,
+    /// Sum of the LP shares of every holder, including locked shares.
+    pub holders_liquidity: Uint256,
+    /// LP shares which can never be withdrawn.
+    pub locked_liquidity: Uint256,
+    /// Whether `total_liquidity == holders_liquidity`.
+    pub holds: bool,
+}
+
+/// The result of simulating a swap.
+#[cw_serde]
+pub struct QuoteResponse {
+    /// The pool the swap trades against.
+    pub pool_id: Uint256,
+    /// Amount received on the target chain.
+    pub amount_out: Uint256,
+    /// Decimal-normalized price of the source token in target tokens, before the swap.
+    pub spot_price: Decimal256,
+}
+
+/// A page of registered chains.
+#[cw_serde]
+pub struct ChainsResponse {
+    /// The registered chains.
+    pub chains: Vec<ChainResponse>,
+}
+
+/// A deposit waiting in the liquidity queue.
+#[cw_serde]
+pub struct QueuedDeposit {
+    /// Position of the deposit in the queue.
+    pub seq: u64,
+    /// The deposit.
+    pub deposit: LiquidityQueueElement,
+}
+
+/// A page of the liquidity queue of a pool.
+#[cw_serde]
+pub struct LiquidityQueueResponse {
+    /// The pool ID.
+    pub pool_id: Uint256,
+    /// The queued deposits.
+    pub deposits: Vec<QueuedDeposit>,
+}
+
+/// A swap waiting in the swap queue.
+#[cw_serde]
+pub struct SwapQueueEntry {
+    /// Sequence number of the swap.
+    pub seq: u64,
+    /// The swap.
+    pub swap: QueuedSwap,
+}
+
+/// A page of the swap queue of a pool.
+#[cw_serde]
+pub struct SwapQueueResponse {
+    /// The pool ID.
+    pub pool_id: Uint256,
+    /// Number of queued swaps.
+    pub depth: u64,
+    /// The queued swaps.
+    pub swaps: Vec<SwapQueueEntry>,
+}
+
+/// A queued swap with its place in the queue.
+#[cw_serde]
+pub struct QueuedSwapResponse {
+    /// The swap.
+    pub swap: QueuedSwap,
+    /// Number of swaps ahead of it.
+    pub position: u64,
+    /// Number of queued swaps.
+    pub depth: u64,
+}
+
+/// A page of payouts awaiting acknowledgement.
+#[cw_serde]
+pub struct PendingSettlementsResponse {
+    /// The pending payouts.
+    pub settlements: Vec<Settlement>,
+}
 
 impl CustomMsg for PalomaMsg {}
@@ -1,8 +1,8 @@
 //! The persistent state of the contract, including pool info and associated queues.
 
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Timestamp, Uint256};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Coin, Empty, Timestamp, Uint256};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 /// Metadata definiting a pool.
 #[cw_serde]
@@ -21,12 +21,23 @@ pub struct PoolInfo {
     pub pending_amount1: Uint256,
     /// Total liquidity available in this pool.
     pub total_liquidity: Uint256,
+    /// Decimals of the source chain token.
+    pub decimals0: u8,
+    /// Decimals of the target chain token.
+    pub decimals1: u8,
     /// Creation time of this `PoolInfo`.
     pub timestamp: Timestamp,
+    /// Seconds after `timestamp` until an un-bootstrapped pool may be refunded and recreated.
+    pub bootstrap_deadline: u64,
     /// Initial creator of `chain0`.
     pub chain0_init_depositor: String,
     /// Initial creator of `chain1`.
     pub chain1_init_depositor: String,
+    /// How counter-side deposits are matched against the liquidity queue.
+    pub matching_policy: MatchingPolicy,
+    /// Seconds swaps are collected into a batch auction before clearing, or `None` to swap
+    /// immediately.
+    pub batch_window: Option<u64>,
 }
 
 /// The chain/token pair which defines a pool.
@@ -42,11 +53,116 @@ pub struct PoolMetaInfo {
     pub token1: String,
 }
 
-/// Mapping from `chain_id` to factory contract `job_id`.
-pub const POOL_FACTORIES: Map<&[u8], String> = Map::new("pool_factories");
+/// Allocation of a counter-side deposit across the queued deposits of a pool.
+#[cw_serde]
+pub enum MatchingPolicy {
+    /// Fill queued deposits strictly in arrival order.
+    Fifo,
+    /// Fill every queued deposit in proportion to its size.
+    ProRata,
+    /// Fill queued deposits in arrival order, at most `cap` each per round, until the deposit is
+    /// used up.
+    RoundRobin {
+        /// Maximum amount of the queued token filled per deposit per round.
+        cap: Uint256,
+    },
+}
+
+impl Default for MatchingPolicy {
+    fn default() -> Self {
+        MatchingPolicy::Fifo
+    }
+}
+
+/// Address allowed to manage chain registrations.
+pub const ADMIN: Item<Addr> = Item::new("admin");
+
+/// How payloads for a chain's factory are encoded.
+#[cw_serde]
+pub enum Encoding {
+    /// Solidity ABI calls with 20-byte hex tokens and accounts.
+    Evm,
+    /// JSON execute messages with bech32 accounts.
+    CosmWasm,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Evm
+    }
+}
+
+/// A registered chain.
+#[cw_serde]
+pub struct ChainInfo {
+    /// The factory contract job.
+    pub factory: String,
+    /// How payloads for the factory are encoded.
+    pub encoding: Encoding,
+    /// Require EIP-55 checksummed addresses on an EVM chain.
+    pub require_checksum: bool,
+    /// Payouts collected per pool before they are sent as one batch, if batching.
+    pub batch_size: Option<u32>,
+}
+
+/// Mapping from `chain_id` to its factory contract `job_id` and payload encoding.
+pub const POOL_FACTORIES: Map<&[u8], ChainInfo> = Map::new("pool_factories");
+
+/// Mapping from `chain_id` to the nonce of the next payload sent to its factory.
+pub const CHAIN_NONCES: Map<&[u8], u64> = Map::new("chain_nonces");
+
+/// Registered metadata of a token on a chain.
+#[cw_serde]
+pub struct TokenInfo {
+    /// Number of decimals of the token.
+    pub decimals: u8,
+    /// Display symbol of the token.
+    pub symbol: String,
+}
+
+/// Mapping from `(chain_id, token)` to token metadata.
+pub const TOKENS: Map<(&[u8], &str), TokenInfo> = Map::new("tokens");
+
+/// Set of `(chain_id, token)` pairs which may be listed in a pool.
+pub const ALLOWED_TOKENS: Map<(&[u8], &str), Empty> = Map::new("allowed_tokens");
 
-/// Mapping from `chain_id` to information about its creation.
-pub const POOLS_INFO: Map<&[u8], PoolInfo> = Map::new("pools_info");
+/// Fee charged for creating a pool with unlisted tokens. Pools may only use listed tokens when
+/// unset.
+pub const POOL_CREATION_FEE: Item<Option<Coin>> = Item::new("pool_creation_fee");
+
+/// Indexes over the pools.
+pub struct PoolsInfoIndexes<'a> {
+    /// Pools by the big-endian bytes of their `chain0_id`.
+    pub chain0: MultiIndex<'a, Vec<u8>, PoolInfo, &'a [u8]>,
+    /// Pools by the big-endian bytes of their `chain1_id`.
+    pub chain1: MultiIndex<'a, Vec<u8>, PoolInfo, &'a [u8]>,
+}
+
+impl<'a> IndexList<PoolInfo> for PoolsInfoIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<PoolInfo>> + '_> {
+        let v: Vec<&dyn Index<PoolInfo>> = vec![&self.chain0, &self.chain1];
+        Box::new(v.into_iter())
+    }
+}
+
+/// A map of pool id to information about its creation.
+pub fn pools_info<'a>() -> IndexedMap<'a, &'a [u8], PoolInfo, PoolsInfoIndexes<'a>> {
+    IndexedMap::new(
+        "pools_info",
+        PoolsInfoIndexes {
+            chain0: MultiIndex::new(
+                |_, pool_info| pool_info.meta.chain0_id.to_be_bytes().to_vec(),
+                "pools_info",
+                "pools_info__chain0",
+            ),
+            chain1: MultiIndex::new(
+                |_, pool_info| pool_info.meta.chain1_id.to_be_bytes().to_vec(),
+                "pools_info",
+                "pools_info__chain1",
+            ),
+        },
+    )
+}
 
 /// Mapping from meta info key to pool id.
 pub const POOL_IDS: Map<&[u8], Uint256> = Map::new("pools_ids");
@@ -54,34 +170,261 @@ pub const POOL_IDS: Map<&[u8], Uint256> = Map::new("pools_ids");
 /// Current numbxer of pools.
 pub const POOLS_COUNT: Item<Uint256> = Item::new("pools_count");
 
-/// Interval before trades are considered invalid.
+/// Default interval before an un-bootstrapped pool may be refunded and recreated.
 pub const DEADLINE: Item<u64> = Item::new("deadline");
 
 /// Mapping from `(pool_id, receiver)` to an amount.
 pub const LIQUIDITY: Map<(&[u8], &[u8]), Uint256> = Map::new("liquidity");
 
-/// Metadata allowing use of a map as a queue.
+/// Owner of LP shares.
+///
+/// Remote owners are held in `LIQUIDITY` and the liquidity queue as `<chain_id>/<address>`, which
+/// can't collide with a Paloma address as those are rejected when containing a `/`.
 #[cw_serde]
-pub struct QueueID {
-    /// Index of head of queue.
-    pub start: u64,
-    /// Length of queue.
-    pub length: u64,
+pub enum LpOwner {
+    /// A Paloma account, which withdraws directly. Must be a valid address, other than
+    /// `LOCKED_LIQUIDITY_HOLDER`.
+    Paloma(Addr),
+    /// An account on a remote chain, which withdraws through relayed requests.
+    Remote {
+        /// Chain of the account.
+        chain_id: Uint256,
+        /// Account address on `chain_id`.
+        address: String,
+    },
 }
 
-/// The `(chain_id, amount, reciever)` triple being processed in the liquidity pool.
+/// Mapping from the `LIQUIDITY` holder of a remote owner to the nonce of its next signed intent.
+pub const SIGNER_NONCES: Map<&str, u64> = Map::new("signer_nonces");
+
+/// Holder in `LIQUIDITY` of the permanently locked `MIN_LIQUIDITY` and rounding remainders.
+pub const LOCKED_LIQUIDITY_HOLDER: &str = "locked_liquidity";
+
+/// A queued deposit being processed in the liquidity pool.
 #[cw_serde]
 pub struct LiquidityQueueElement {
     /// Transferring chain id.
     pub chain_id: Uint256,
     /// Amount to transfer.
     pub amount: Uint256,
+    /// Depositing address on `chain_id`, refunded if the deposit is returned.
+    pub sender: String,
     /// Receiving address.
     pub receiver: Addr,
 }
 
-/// Mapping from pool id to queue metadata, which can be used to index into `LIQUIDITY_QUEUE`.
-pub const LIQUIDITY_QUEUE_IDS: Map<&[u8], QueueID> = Map::new("liquidity_queue_ids");
+/// Indexes over the liquidity queue.
+pub struct LiquidityQueueIndexes<'a> {
+    /// Queued deposits by the address receiving their LP shares.
+    pub owner: MultiIndex<'a, Addr, LiquidityQueueElement, (&'a [u8], u64)>,
+}
+
+impl<'a> IndexList<LiquidityQueueElement> for LiquidityQueueIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<LiquidityQueueElement>> + '_> {
+        let v: Vec<&dyn Index<LiquidityQueueElement>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+/// A map of `(pool_id, sequence)` to a queued deposit, in arrival order within a pool.
+pub fn liquidity_queue<'a>(
+) -> IndexedMap<'a, (&'a [u8], u64), LiquidityQueueElement, LiquidityQueueIndexes<'a>> {
+    IndexedMap::new(
+        "liquidity_queue",
+        LiquidityQueueIndexes {
+            owner: MultiIndex::new(
+                |_, element| element.receiver.clone(),
+                "liquidity_queue",
+                "liquidity_queue__owner",
+            ),
+        },
+    )
+}
+
+/// Mapping from pool id to the sequence number of its next queued deposit.
+pub const LIQUIDITY_QUEUE_SEQ: Map<&[u8], u64> = Map::new("liquidity_queue_seq");
+
+/// Mapping from pool id to a counter-side deposit part-way through matching the liquidity queue.
+/// Later deposits wait behind it until it is matched with `ContinueMatching`.
+pub const MATCHING: Map<&[u8], LiquidityQueueElement> = Map::new("matching");
+
+/// Mapping from `(pool_id, sequence)` to counter-side deposits which arrived while another was
+/// part-way through matching, each matched or queued by a later `ContinueMatching`.
+pub const MATCHING_BACKLOG: Map<(&[u8], u64), LiquidityQueueElement> = Map::new("matching_backlog");
+
+/// Pro-rata round of the deposit in `MATCHING` over the queued deposits it has yet to reach.
+#[cw_serde]
+pub struct MatchingShare {
+    /// Amount of the queued token still to be shared out in the round.
+    pub needed: Uint256,
+    /// Amount queued in the round's deposits not reached yet.
+    pub queued: Uint256,
+    /// Sequence number of the next queued deposit to reach.
+    pub next: u64,
+    /// Sequence number after the last queued deposit in the round.
+    pub end: u64,
+}
+
+/// Mapping from pool id to the pro-rata round of its deposit in `MATCHING`, carried across
+/// `ContinueMatching` batches so the whole queue is matched at one ratio.
+pub const MATCHING_SHARE: Map<&[u8], MatchingShare> = Map::new("matching_share");
+
+/// How the relayed amount of a swap is traded.
+#[cw_serde]
+pub enum SwapMode {
+    /// Swap the whole amount.
+    ExactIn {
+        /// Minimum amount to receive on the target chain.
+        min_amount_out: Uint256,
+    },
+    /// Deliver exactly `amount_out`, refunding the unused part of the amount on the source chain.
+    ExactOut {
+        /// Amount to receive on the target chain.
+        amount_out: Uint256,
+        /// Maximum amount of the source token to spend.
+        max_amount_in: Uint256,
+    },
+}
+
+/// A swap waiting for liquidity on its target chain.
+#[cw_serde]
+pub struct QueuedSwap {
+    /// Source chain id.
+    pub chain_from_id: Uint256,
+    /// Raw amount of the source token.
+    pub amount: Uint256,
+    /// How the amount is traded.
+    pub mode: SwapMode,
+    /// Source account, refunded if the swap expires.
+    pub sender: String,
+    /// Target account.
+    pub receiver: String,
+    /// Time after which the swap is refunded instead of filled.
+    pub expiry: Timestamp,
+}
+
+/// A map of `(pool_id, sequence)` to a queued swap, in arrival order within a pool.
+pub const SWAP_QUEUE: Map<(&[u8], u64), QueuedSwap> = Map::new("swap_queue");
+
+/// Mapping from pool id to the sequence number of its next queued swap.
+pub const SWAP_QUEUE_SEQ: Map<&[u8], u64> = Map::new("swap_queue_seq");
+
+/// A swap waiting in a batch auction.
+#[cw_serde]
+pub struct SwapOrder {
+    /// Source chain id.
+    pub chain_from_id: Uint256,
+    /// Raw amount of the source token.
+    pub amount: Uint256,
+    /// Minimum amount to receive on the target chain.
+    pub min_amount_out: Uint256,
+    /// Source account, refunded if the order is not filled.
+    pub sender: String,
+    /// Target account.
+    pub receiver: String,
+}
+
+/// Swaps collected for clearing at one uniform price.
+#[cw_serde]
+pub struct SwapBatch {
+    /// Time the first order arrived.
+    pub opened: Timestamp,
+    /// Orders in arrival order.
+    pub orders: Vec<SwapOrder>,
+}
+
+/// Mapping from pool id to its open batch auction.
+pub const SWAP_BATCHES: Map<&[u8], SwapBatch> = Map::new("swap_batches");
+
+/// Set of `(chain_id, tx_hash, log_index)` deposits already relayed, so none is credited twice.
+pub const PROCESSED_DEPOSITS: Map<(&[u8], &str, u64), Empty> = Map::new("processed_deposits");
+
+/// Address allowed to acknowledge or fail payouts on behalf of Compass.
+pub const RELAYER: Item<Option<Addr>> = Item::new("relayer");
+
+/// How a payout is reconciled if it fails on its target chain.
+#[cw_serde]
+pub enum PayoutKind {
+    /// A swap payout, reversed on failure with `amount_in` refunded to `sender`.
+    Swap {
+        /// Source chain id of the swap.
+        chain_from_id: Uint256,
+        /// Raw amount of the source token traded.
+        amount_in: Uint256,
+        /// Source account.
+        sender: String,
+    },
+    /// A withdrawal leg, returned to the reserves and `liquidity` LP shares of `holder` on
+    /// failure.
+    Withdrawal {
+        /// The LP who burned the shares.
+        holder: Addr,
+        /// LP shares burned for this leg.
+        liquidity: Uint256,
+    },
+    /// A refund of funds held outside the reserves.
+    Refund,
+}
+
+/// A payout awaiting acknowledgement of its delivery.
+#[cw_serde]
+pub struct Settlement {
+    /// Sequence number of the payout.
+    pub nonce: u64,
+    /// The pool paying out.
+    pub pool_id: Uint256,
+    /// Target chain id.
+    pub chain_id: Uint256,
+    /// Target account.
+    pub recipient: String,
+    /// Raw amount paid out.
+    pub amount: Uint256,
+    /// How the payout is rolled back.
+    pub kind: PayoutKind,
+}
+
+/// Mapping from nonce to pending payouts.
+pub const PENDING_SETTLEMENTS: Map<u64, Settlement> = Map::new("pending_settlements");
+
+/// Mapping from `(chain_id, pool_id)` to payouts collected to be sent as one batch.
+pub const OUTBOX: Map<(&[u8], &[u8]), Vec<Settlement>> = Map::new("outbox");
+
+/// Number of payouts made, which is the nonce of the next payout.
+pub const SETTLEMENTS_COUNT: Item<u64> = Item::new("settlements_count");
+
+/// A double-sided deposit whose two relayed legs are minted together.
+#[cw_serde]
+pub struct DepositIntent {
+    /// Pool to deposit into.
+    pub pool_id: Uint256,
+    /// Address receiving the LP shares.
+    pub owner: Addr,
+    /// Expected deposit on the source chain.
+    pub amount0: Uint256,
+    /// Expected deposit on the target chain.
+    pub amount1: Uint256,
+    /// Source reserve when the intent was registered.
+    pub reserve0: Uint256,
+    /// Target reserve when the intent was registered.
+    pub reserve1: Uint256,
+    /// Maximum price movement, in basis points, before the legs are refunded instead.
+    pub tolerance_bps: u16,
+    /// Time after which arrived legs are refunded.
+    pub expiry: Timestamp,
+    /// Depositing address of the source leg.
+    pub sender0: String,
+    /// Depositing address of the target leg.
+    pub sender1: String,
+    /// Whether the source leg has arrived.
+    pub arrived0: bool,
+    /// Whether the target leg has arrived.
+    pub arrived1: bool,
+}
+
+/// Mapping from intent id to a pending deposit intent.
+pub const DEPOSIT_INTENTS: Map<u64, DepositIntent> = Map::new("deposit_intents");
 
-/// A map of (pool_id, queue_id) to (chain_id, amount, depositor).
-pub const LIQUIDITY_QUEUE: Map<(&[u8], &[u8]), LiquidityQueueElement> = Map::new("liquidity_queue");
+/// Number of deposit intents registered so far, used to assign intent ids.
+pub const DEPOSIT_INTENTS_COUNT: Item<u64> = Item::new("deposit_intents_count");
@@ -1,10 +1,151 @@
 //! Smoke tests.
 
-use crate::contract::{execute, instantiate};
-use crate::msg::{ExecuteMsg, InstantiateMsg};
+use crate::abi::{Envelope, Evm, FactoryExecuteMsg, PayloadEncoder, Transfer};
+use crate::contract::{execute, instantiate, query};
+use crate::eip712;
+use crate::msg::{
+    AllowedTokensResponse, ChainResponse, ChainsResponse, ExecuteMsg, InstantiateMsg,
+    LiquidityInvariantResponse, LiquidityQueueResponse, PalomaMsg, PendingSettlementsResponse,
+    QueryMsg, QueuedSwapResponse, QuoteResponse, SignedAction, SwapQueueResponse, TokenResponse,
+    ZapIn,
+};
+use crate::state::{
+    Encoding, LiquidityQueueElement, LpOwner, MatchingPolicy, PayoutKind, PoolInfo, Settlement,
+    SwapBatch, SwapMode, LIQUIDITY, LOCKED_LIQUIDITY_HOLDER,
+};
 use crate::ContractError;
 use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{
+    coin, from_binary, from_slice, Addr, BankMsg, Binary, CosmosMsg, Decimal256, Deps, DepsMut,
+    Response, Timestamp, Uint256,
+};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{recoverable, SigningKey};
+use k256::elliptic_curve::sec1::ToEncodedPoint;
+use sha3::{Digest, Keccak256};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const EVM: Evm = Evm {
+    require_checksum: false,
+};
+
+/// A fresh source chain transaction hash for each relayed deposit.
+fn next_tx_hash() -> String {
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    format!("0x{:064x}", COUNT.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Register token metadata and list the token for pool creation.
+fn list_token(
+    mut deps: DepsMut,
+    chain_id: Uint256,
+    token: &str,
+    decimals: u8,
+    symbol: &str,
+) -> Result<(), ContractError> {
+    for msg in [
+        ExecuteMsg::RegisterToken {
+            chain_id,
+            token: token.to_string(),
+            decimals,
+            symbol: symbol.to_string(),
+        },
+        ExecuteMsg::AllowToken {
+            chain_id,
+            token: token.to_string(),
+        },
+    ] {
+        let r = execute(deps.branch(), mock_env(), mock_info("admin0000", &[]), msg)?;
+        assert_eq!(r.messages.len(), 0);
+    }
+    Ok(())
+}
+
+const TOKEN0: &str = "0123456789012345678901234567890123456789";
+const TOKEN1: &str = "abcdefabcdefabcdefabcdefabcdefabcdefabcd";
+const DEPOSITOR0: &str = "0x1111111111111111111111111111111111111111";
+const DEPOSITOR1: &str = "0x2222222222222222222222222222222222222222";
+
+/// Instantiate the contract with chains 42 and 52 and list `TOKEN0` and `TOKEN1` on them.
+fn setup(mut deps: DepsMut) -> Result<(Uint256, Uint256), ContractError> {
+    let info = mock_info("admin0000", &[]);
+    instantiate(
+        deps.branch(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg { deadline: 1000 },
+    )?;
+    let (chain0_id, chain1_id): (Uint256, Uint256) = (42u32.into(), 52u32.into());
+    for (chain_id, factory, token) in [(chain0_id, "abcd", TOKEN0), (chain1_id, "wxyz", TOKEN1)] {
+        execute(
+            deps.branch(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory: factory.to_string(),
+                encoding: None,
+                require_checksum: None,
+            },
+        )?;
+        list_token(deps.branch(), chain_id, token, 18, "WETH")?;
+    }
+    execute(
+        deps.branch(),
+        mock_env(),
+        info,
+        ExecuteMsg::SetRelayer {
+            relayer: Some(Addr::unchecked("relayer")),
+        },
+    )?;
+    Ok((chain0_id, chain1_id))
+}
+
+/// Create a pool of `TOKEN0` and `TOKEN1` bootstrapped by `DEPOSITOR0` and `DEPOSITOR1`.
+fn create_pool_msg(
+    (chain0_id, chain1_id): (Uint256, Uint256),
+    bootstrap_deadline: Option<u64>,
+) -> ExecuteMsg {
+    ExecuteMsg::CreatePool {
+        chain0_id,
+        chain1_id,
+        token0: TOKEN0.to_string(),
+        token1: TOKEN1.to_string(),
+        chain0_init_depositor: DEPOSITOR0.to_string(),
+        chain1_init_depositor: DEPOSITOR1.to_string(),
+        bootstrap_deadline,
+        matching_policy: None,
+        batch_window: None,
+    }
+}
+
+/// Relay a deposit into pool 0.
+fn deposit(
+    deps: DepsMut,
+    chain_id: Uint256,
+    token: &str,
+    sender: &str,
+    receiver: &str,
+    amount: u128,
+) -> Result<Response<PalomaMsg>, ContractError> {
+    execute(
+        deps,
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::AddLiquidity {
+            pool_id: 0u32.into(),
+            chain_id,
+            token: token.to_string(),
+            amount: amount.into(),
+            sender: sender.to_string(),
+            receiver: LpOwner::Paloma(Addr::unchecked(receiver)),
+            zap: None,
+            intent_id: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    )
+}
 
 /// Test instantiating the contract, creating a pool, adding liquidity and making a trade.
 #[test]
@@ -18,6 +159,14 @@ fn happy_path() -> Result<(), ContractError> {
         info.clone(),
         InstantiateMsg { deadline: 1000 },
     )?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SetRelayer {
+            relayer: Some(Addr::unchecked("relayer")),
+        },
+    )?;
 
     let (chain0_id, chain1_id) = (42u32.into(), 52u32.into());
     let (token0, token1) = (
@@ -25,18 +174,27 @@ fn happy_path() -> Result<(), ContractError> {
         "abcdefabcdefabcdefabcdefabcdefabcdefabcd".to_string(),
     );
     let (factory0, factory1) = ("abcd".to_string(), "wxyz".to_string());
-    let (sender0, sender1) = ("addr01234".to_string(), "addr98765".to_string());
+    let (sender0, sender1) = (DEPOSITOR0.to_string(), DEPOSITOR1.to_string());
 
     for (chain_id, factory) in [(chain0_id, factory0), (chain1_id, factory1)] {
         let r = execute(
             deps.as_mut(),
             mock_env(),
             info.clone(),
-            ExecuteMsg::RegisterChain { chain_id, factory },
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory,
+                encoding: None,
+                require_checksum: None,
+            },
         )?;
         assert_eq!(r.messages.len(), 0);
     }
 
+    for (chain_id, token) in [(chain0_id, &token0), (chain1_id, &token1)] {
+        list_token(deps.as_mut(), chain_id, token, 18, "WETH")?;
+    }
+
     let r = execute(
         deps.as_mut(),
         mock_env(),
@@ -48,45 +206,3164 @@ fn happy_path() -> Result<(), ContractError> {
             token1: token1.clone(),
             chain0_init_depositor: sender0.clone(),
             chain1_init_depositor: sender1.clone(),
+            bootstrap_deadline: None,
+            matching_policy: None,
+            batch_window: None,
         },
     )?;
     assert_eq!(r.messages.len(), 2);
 
     for (chain_id, token, sender) in [
-        (chain0_id.clone(), token0.clone(), sender0.clone()),
-        (chain1_id.clone(), token1.clone(), sender1.clone()),
+        (chain0_id, token0.clone(), sender0.clone()),
+        (chain1_id, token1.clone(), sender1.clone()),
     ] {
         let r = execute(
             deps.as_mut(),
             mock_env(),
-            info.clone(),
+            mock_info("relayer", &[]),
             ExecuteMsg::AddLiquidity {
                 pool_id: 0u32.into(),
                 chain_id,
                 token,
                 amount: 10000u32.into(),
                 sender,
-                receiver: Addr::unchecked("addr01234"),
+                receiver: LpOwner::Paloma(Addr::unchecked("addr01234")),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
             },
         )?;
         assert_eq!(r.messages.len(), 0);
     }
 
-    //let r = execute(
-    //    deps.as_mut(),
-    //    mock_env(),
-    //    info.clone(),
-    //    ExecuteMsg::Swap {
-    //        chain_from_id: chain0_id,
-    //        chain_to_id: chain1_id,
-    //        token_from: token0,
-    //        token_to: token1,
-    //        sender: sender0,
-    //        receiver: sender1,
-    //        amount: 5000u32.into(),
-    //    },
-    //)?;
-    //assert_eq!(r.messages.len(), 2);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::Swap {
+            chain_from_id: chain0_id,
+            chain_to_id: chain1_id,
+            token_from: token0,
+            token_to: token1,
+            sender: sender0,
+            receiver: "0x9876598765987659876598765987659876598765".to_string(),
+            amount: 5000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: 3000u32.into(),
+            },
+            queue_expiry: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+
+    Ok(())
+}
+
+/// Test rotating and removing chain factories, and listing them.
+#[test]
+fn chain_registration() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+
+    let info = mock_info("admin0000", &[]);
+    let _ = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg { deadline: 1000 },
+    )?;
+
+    let (chain0_id, chain1_id): (Uint256, Uint256) = (42u32.into(), 52u32.into());
+    for (chain_id, factory) in [(chain0_id, "abcd"), (chain1_id, "wxyz")] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory: factory.to_string(),
+                encoding: None,
+                require_checksum: None,
+            },
+        )?;
+    }
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::RegisterChain {
+            chain_id: chain0_id,
+            factory: "efgh".to_string(),
+            encoding: None,
+            require_checksum: None,
+        },
+    );
+    assert!(matches!(r, Err(ContractError::ChainExists { .. })));
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::UpdateChain {
+            chain_id: chain0_id,
+            factory: "efgh".to_string(),
+        },
+    );
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::UpdateChain {
+            chain_id: chain0_id,
+            factory: "efgh".to_string(),
+        },
+    )?;
+
+    let (token0, token1) = (
+        "0123456789012345678901234567890123456789".to_string(),
+        "abcdefabcdefabcdefabcdefabcdefabcdefabcd".to_string(),
+    );
+    for (chain_id, token) in [(chain0_id, &token0), (chain1_id, &token1)] {
+        list_token(deps.as_mut(), chain_id, token, 18, "WETH")?;
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::CreatePool {
+            chain0_id,
+            chain1_id,
+            token0,
+            token1,
+            chain0_init_depositor: DEPOSITOR0.to_string(),
+            chain1_init_depositor: DEPOSITOR1.to_string(),
+            bootstrap_deadline: None,
+            matching_policy: None,
+            batch_window: None,
+        },
+    )?;
+    for chain_id in [chain0_id, chain1_id] {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::DeregisterChain { chain_id },
+        );
+        assert!(matches!(r, Err(ContractError::ChainInUse { .. })));
+    }
+
+    let chains: ChainsResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Chains {
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    assert_eq!(
+        chains.chains,
+        vec![
+            ChainResponse {
+                chain_id: chain0_id,
+                factory: "efgh".to_string(),
+                encoding: Encoding::Evm,
+                require_checksum: false,
+                batch_size: None,
+                nonce: 1,
+            },
+            ChainResponse {
+                chain_id: chain1_id,
+                factory: "wxyz".to_string(),
+                encoding: Encoding::Evm,
+                require_checksum: false,
+                batch_size: None,
+                nonce: 1,
+            },
+        ]
+    );
+
+    let chains: ChainsResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Chains {
+            start_after: Some(chain0_id),
+            limit: None,
+        },
+    )?)?;
+    assert_eq!(chains.chains.len(), 1);
+    assert_eq!(chains.chains[0].chain_id, chain1_id);
+
+    Ok(())
+}
+
+/// Test that pricing and LP minting normalize tokens with different decimals.
+#[test]
+fn decimal_normalized_pricing() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+
+    let info = mock_info("admin0000", &[]);
+    let _ = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg { deadline: 1000 },
+    )?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SetRelayer {
+            relayer: Some(Addr::unchecked("relayer")),
+        },
+    )?;
+
+    let (chain0_id, chain1_id): (Uint256, Uint256) = (1u32.into(), 137u32.into());
+    let (usdc, dai) = (
+        "a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string(),
+        "8f3cf7ad23cd3cadbd9735aff958023239c6a063".to_string(),
+    );
+    let (sender0, sender1) = (DEPOSITOR0.to_string(), DEPOSITOR1.to_string());
+    for (chain_id, factory, token, decimals, symbol) in [
+        (chain0_id, "abcd", usdc.clone(), 6, "USDC"),
+        (chain1_id, "wxyz", dai.clone(), 18, "DAI"),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory: factory.to_string(),
+                encoding: None,
+                require_checksum: None,
+            },
+        )?;
+        list_token(deps.as_mut(), chain_id, &token, decimals, symbol)?;
+    }
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::CreatePool {
+            chain0_id,
+            chain1_id,
+            token0: usdc.clone(),
+            token1: "0000000000000000000000000000000000000001".to_string(),
+            chain0_init_depositor: sender0.clone(),
+            chain1_init_depositor: sender1.clone(),
+            bootstrap_deadline: None,
+            matching_policy: None,
+            batch_window: None,
+        },
+    );
+    assert!(matches!(r, Err(ContractError::TokenNotAllowed { .. })));
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::CreatePool {
+            chain0_id,
+            chain1_id,
+            token0: usdc.clone(),
+            token1: dai.clone(),
+            chain0_init_depositor: sender0.clone(),
+            chain1_init_depositor: sender1.clone(),
+            bootstrap_deadline: None,
+            matching_policy: None,
+            batch_window: None,
+        },
+    )?;
+
+    // 1000 USDC against 1000 DAI.
+    for (chain_id, token, sender, amount) in [
+        (
+            chain0_id,
+            usdc.clone(),
+            sender0,
+            Uint256::from(1_000_000_000u128),
+        ),
+        (
+            chain1_id,
+            dai.clone(),
+            sender1,
+            Uint256::from(1_000_000_000_000_000_000_000u128),
+        ),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::AddLiquidity {
+                pool_id: 0u32.into(),
+                chain_id,
+                token,
+                amount,
+                sender,
+                receiver: LpOwner::Paloma(Addr::unchecked("addr01234")),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+    }
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(
+        pool_info.total_liquidity,
+        Uint256::from(1_000_000_000_000_000_000_000u128)
+    );
+
+    let quote: QuoteResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Quote {
+            chain_from_id: chain0_id,
+            chain_to_id: chain1_id,
+            token_from: usdc.clone(),
+            token_to: dai.clone(),
+            amount: 1_000_000u32.into(),
+        },
+    )?)?;
+    assert_eq!(quote.spot_price, Decimal256::one());
+    // 1 USDC buys just under 0.997 DAI.
+    assert_eq!(quote.amount_out, Uint256::from(996_006_981_039_903_216u128));
+
+    let token: TokenResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Token {
+            chain_id: chain0_id,
+            token: usdc,
+        },
+    )?)?;
+    assert_eq!(token.decimals, 6);
+    assert_eq!(token.symbol, "USDC");
+
+    Ok(())
+}
+
+/// Test that pools can only list allowed tokens unless the creation fee is paid.
+#[test]
+fn token_allowlist() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+
+    let info = mock_info("admin0000", &[]);
+    let _ = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg { deadline: 1000 },
+    )?;
+
+    let (chain0_id, chain1_id): (Uint256, Uint256) = (42u32.into(), 52u32.into());
+    let (token0, token1) = (
+        "0123456789012345678901234567890123456789".to_string(),
+        "abcdefabcdefabcdefabcdefabcdefabcdefabcd".to_string(),
+    );
+    for (chain_id, factory) in [(chain0_id, "abcd"), (chain1_id, "wxyz")] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory: factory.to_string(),
+                encoding: None,
+                require_checksum: None,
+            },
+        )?;
+    }
+    list_token(deps.as_mut(), chain0_id, &token0, 18, "WETH")?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::RegisterToken {
+            chain_id: chain1_id,
+            token: token1.clone(),
+            decimals: 18,
+            symbol: "WETH".to_string(),
+        },
+    )?;
+
+    let create_pool = ExecuteMsg::CreatePool {
+        chain0_id,
+        chain1_id,
+        token0: token0.clone(),
+        token1: token1.clone(),
+        chain0_init_depositor: DEPOSITOR0.to_string(),
+        chain1_init_depositor: DEPOSITOR1.to_string(),
+        bootstrap_deadline: None,
+        matching_policy: None,
+        batch_window: None,
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[]),
+        create_pool.clone(),
+    );
+    assert!(matches!(r, Err(ContractError::TokenNotAllowed { .. })));
+
+    // A free permissionless mode is rejected.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SetPoolCreationFee {
+            fee: Some(coin(0, "ugrain")),
+        },
+    );
+    assert!(matches!(r, Err(ContractError::ZeroAmount {})));
+
+    let fee = coin(100, "ugrain");
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SetPoolCreationFee {
+            fee: Some(fee.clone()),
+        },
+    )?;
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", &[coin(99, "ugrain")]),
+        create_pool.clone(),
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InsufficientCreationFee { .. })
+    ));
+    for funds in [
+        vec![coin(101, "ugrain")],
+        vec![fee.clone(), coin(1, "uatom")],
+    ] {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("creator", &funds),
+            create_pool.clone(),
+        );
+        assert!(matches!(r, Err(ContractError::UnexpectedFunds {})));
+    }
+    // Paying the fee lifts the allowlist, but tokens must still be registered.
+    let unregistered = ExecuteMsg::CreatePool {
+        chain0_id,
+        chain1_id,
+        token0: token0.clone(),
+        token1: "0000000000000000000000000000000000000001".to_string(),
+        chain0_init_depositor: DEPOSITOR0.to_string(),
+        chain1_init_depositor: DEPOSITOR1.to_string(),
+        bootstrap_deadline: None,
+        matching_policy: None,
+        batch_window: None,
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", std::slice::from_ref(&fee)),
+        unregistered,
+    );
+    assert!(matches!(r, Err(ContractError::TokenNotRegistered { .. })));
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("creator", std::slice::from_ref(&fee)),
+        create_pool,
+    )?;
+    assert_eq!(r.messages.len(), 3);
+    assert_eq!(
+        r.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "admin0000".to_string(),
+            amount: vec![fee],
+        })
+    );
+
+    let allowed: AllowedTokensResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::AllowedTokens {
+            chain_id: chain0_id,
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    // Tokens are listed in canonical form.
+    assert_eq!(allowed.tokens, vec![format!("0x{token0}")]);
+
+    Ok(())
+}
+
+/// Test rejecting relayed deposits which don't fit their pool.
+#[test]
+fn invalid_deposit() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+
+    let r = deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 0);
+    assert!(matches!(r, Err(ContractError::ZeroAmount {})));
+    for (chain_id, token) in [(chains.0, TOKEN1), (chains.1, TOKEN0)] {
+        let r = deposit(deps.as_mut(), chain_id, token, DEPOSITOR0, "alice", 1000);
+        assert!(matches!(r, Err(ContractError::InvalidToken { .. })));
+    }
+    // Only the initial depositors may bootstrap the pool.
+    let r = deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR1, "alice", 1000);
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 1000)?;
+
+    Ok(())
+}
+
+/// Test rejecting pools whose chains are not in ascending order.
+#[test]
+fn unordered_chains() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let info = mock_info("admin0000", &[]);
+    for pair in [(chains.1, chains.0), (chains.0, chains.0)] {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            create_pool_msg(pair, None),
+        );
+        assert!(matches!(
+            r,
+            Err(ContractError::UnorderedChains { chain0_id, chain1_id })
+                if (chain0_id, chain1_id) == pair
+        ));
+    }
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        create_pool_msg(chains, None),
+    )?;
+
+    Ok(())
+}
+
+/// Test that a pool which misses its own bootstrap deadline refunds its queue when recreated.
+#[test]
+fn bootstrap_refund() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let info = mock_info("admin0000", &[]);
+
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        create_pool_msg(chains, Some(100)),
+    )?;
+    for amount in [4000u32, 6000u32] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::AddLiquidity {
+                pool_id: 0u32.into(),
+                chain_id: chains.0,
+                token: TOKEN0.to_string(),
+                amount: amount.into(),
+                sender: DEPOSITOR0.to_string(),
+                receiver: LpOwner::Paloma(Addr::unchecked("addr01234")),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+    }
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        create_pool_msg(chains, None),
+    );
+    assert!(matches!(r, Err(ContractError::PoolExists { .. })));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::RefundBootstrap {
+            pool_id: 0u32.into(),
+        },
+    );
+    assert!(matches!(r, Err(ContractError::BootstrapNotExpired { .. })));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(101);
+    let r = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        create_pool_msg(chains, None),
+    )?;
+    // Two refunds to the chain0 factory, then the two pool creations.
+    assert_eq!(r.messages.len(), 4);
+    for msg in &r.messages[..2] {
+        assert!(matches!(
+            &msg.msg,
+            CosmosMsg::Custom(PalomaMsg { job_id, .. }) if job_id == "abcd"
+        ));
+    }
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(pool_info.pending_amount0.is_zero());
+    assert_eq!(pool_info.bootstrap_deadline, 1000);
+    assert_eq!(pool_info.timestamp, env.block.time);
+
+    let r = execute(
+        deps.as_mut(),
+        env,
+        info,
+        ExecuteMsg::RefundBootstrap {
+            pool_id: 0u32.into(),
+        },
+    );
+    assert!(matches!(r, Err(ContractError::BootstrapNotExpired { .. })));
+
+    Ok(())
+}
+
+/// Test that bootstrapping credits every queued deposit in proportion to its amount.
+#[test]
+fn bootstrap_receivers() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 6000)?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "carol", 4000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "bob", 10000)?;
+
+    let pool_id_key = Uint256::zero().to_be_bytes();
+    for (holder, liquidity) in [
+        ("alice", 2700u32),
+        ("carol", 1800u32),
+        ("bob", 4500u32),
+        (LOCKED_LIQUIDITY_HOLDER, 1000u32),
+    ] {
+        assert_eq!(
+            LIQUIDITY.load(&deps.storage, (pool_id_key.as_slice(), holder.as_bytes()))?,
+            Uint256::from(liquidity)
+        );
+    }
+
+    Ok(())
+}
+
+/// Test that locked and rounding liquidity is credited so the pool shares add up.
+#[test]
+fn liquidity_invariant() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let info = mock_info("admin0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        create_pool_msg(chains, None),
+    )?;
+
+    for (chain_id, token, sender, receiver, amount) in [
+        (chains.0, TOKEN0, DEPOSITOR0, "alice", 10001u32),
+        (chains.1, TOKEN1, DEPOSITOR1, "bob", 10001u32),
+        (chains.0, TOKEN0, DEPOSITOR0, "chris", 3001u32),
+        (chains.1, TOKEN1, DEPOSITOR1, "derek", 3000u32),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::AddLiquidity {
+                pool_id: 0u32.into(),
+                chain_id,
+                token: token.to_string(),
+                amount: amount.into(),
+                sender: sender.to_string(),
+                receiver: LpOwner::Paloma(Addr::unchecked(receiver)),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+    }
+
+    let pool_id_key = Uint256::zero().to_be_bytes();
+    for (holder, liquidity) in [
+        ("alice", 4500u32),
+        ("bob", 4500u32),
+        ("chris", 1500u32),
+        ("derek", 1500u32),
+        (LOCKED_LIQUIDITY_HOLDER, 1001u32),
+    ] {
+        assert_eq!(
+            LIQUIDITY.load(&deps.storage, (pool_id_key.as_slice(), holder.as_bytes()))?,
+            Uint256::from(liquidity)
+        );
+    }
+
+    let invariant: LiquidityInvariantResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityInvariant {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(invariant.holds);
+    assert_eq!(invariant.total_liquidity, Uint256::from(13001u32));
+    assert_eq!(invariant.locked_liquidity, Uint256::from(1001u32));
+
+    Ok(())
+}
+
+/// Test that removing liquidity checks the LP balance, slippage bounds and deadline.
+#[test]
+fn remove_liquidity() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 20000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 5000)?;
+
+    let deadline = mock_env().block.time.seconds();
+    let remove = |amount: u32, min_amount0: u32, deadline: u64| ExecuteMsg::RemoveLiquidity {
+        pool_id: 0u32.into(),
+        amount: amount.into(),
+        receiver0: DEPOSITOR0.to_string(),
+        receiver1: DEPOSITOR1.to_string(),
+        min_amount0: min_amount0.into(),
+        min_amount1: Uint256::zero(),
+        deadline,
+    };
+
+    // alice holds 10000 - 1000 locked shares.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(9001, 0, deadline),
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InsufficientLiquidity { .. })
+    ));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("bob", &[]),
+        remove(1, 0, deadline),
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InsufficientLiquidity { .. })
+    ));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(4500, 0, deadline - 1),
+    );
+    assert!(matches!(r, Err(ContractError::Expired { .. })));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(4500, 9001, deadline),
+    );
+    assert!(matches!(r, Err(ContractError::SlippageExceeded { .. })));
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(4500, 9000, deadline),
+    )?;
+    assert_eq!(r.messages.len(), 2);
+    let (job_id, payload) = match &r.messages[0].msg {
+        CosmosMsg::Custom(PalomaMsg { job_id, payload }) => (job_id, payload),
+        _ => panic!("expected a paloma message"),
+    };
+    assert_eq!(job_id, "abcd");
+    // remove_liquidity(pool_id = 0, amount = 9000, recipient = DEPOSITOR0, nonce = 1, deadline)
+    assert_eq!(payload.0[4..36], [0u8; 32]);
+    assert_eq!(payload.0[36..68], Uint256::from(9000u32).to_be_bytes());
+    assert_eq!(payload.0[100..132], Uint256::from(1u8).to_be_bytes());
+    let abi = ethabi::Contract::load(include_bytes!("../abi/factory.json").as_slice()).unwrap();
+    assert_eq!(
+        payload.0[..4],
+        abi.function("remove_liquidity").unwrap().short_signature()
+    );
+    assert_eq!(
+        payload.0[132..164],
+        Uint256::from(mock_env().block.time.seconds() + 3600).to_be_bytes()
+    );
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(11000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(2750u32));
+    let invariant: LiquidityInvariantResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityInvariant {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(invariant.holds);
+
+    Ok(())
+}
+
+/// Test withdrawing LP shares as a single token on one chain.
+#[test]
+fn remove_liquidity_single() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    let remove = |chain_id: Uint256, min_amount_out: u32| ExecuteMsg::RemoveLiquiditySingle {
+        pool_id: 0u32.into(),
+        amount: 4500u32.into(),
+        chain_id,
+        receiver: DEPOSITOR1.to_string(),
+        min_amount_out: min_amount_out.into(),
+        deadline: mock_env().block.time.seconds(),
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(7u32.into(), 0),
+    );
+    assert!(matches!(r, Err(ContractError::ChainNotInPool { .. })));
+    // 4500 token1 plus 4500 token0 swapped into a 5500/5500 pool.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(chains.1, 6971),
+    );
+    assert!(matches!(r, Err(ContractError::SlippageExceeded { .. })));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        remove(chains.1, 6970),
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    let (job_id, payload) = match &r.messages[0].msg {
+        CosmosMsg::Custom(PalomaMsg { job_id, payload }) => (job_id, payload),
+        _ => panic!("expected a paloma message"),
+    };
+    assert_eq!(job_id, "wxyz");
+    assert_eq!(payload.0[36..68], Uint256::from(6970u32).to_be_bytes());
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(10000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(3030u32));
+    assert_eq!(pool_info.total_liquidity, Uint256::from(5500u32));
+
+    Ok(())
+}
+
+/// Test that a zapped single-sided deposit mints LP shares without queueing.
+#[test]
+fn zap_in() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    let zap = |min_liquidity: u32| ExecuteMsg::AddLiquidity {
+        pool_id: 0u32.into(),
+        chain_id: chains.0,
+        token: TOKEN0.to_string(),
+        amount: 2000u32.into(),
+        sender: DEPOSITOR0.to_string(),
+        receiver: LpOwner::Paloma(Addr::unchecked("bob")),
+        zap: Some(ZapIn {
+            min_liquidity: min_liquidity.into(),
+        }),
+        intent_id: None,
+        tx_hash: next_tx_hash(),
+        log_index: 0,
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        zap(952),
+    );
+    assert!(matches!(r, Err(ContractError::SlippageExceeded { .. })));
+    // 955 token0 is swapped for 869 token1, and both are added back as liquidity.
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        zap(951),
+    )?;
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(12000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(10000u32));
+    assert!(pool_info.pending_amount0.is_zero());
+    assert_eq!(
+        LIQUIDITY.load(
+            &deps.storage,
+            (Uint256::zero().to_be_bytes().as_slice(), b"bob")
+        )?,
+        Uint256::from(951u32)
+    );
+
+    // A zap too small to mint any liquidity is rejected rather than absorbed by the pool.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::AddLiquidity {
+            pool_id: 0u32.into(),
+            chain_id: chains.0,
+            token: TOKEN0.to_string(),
+            amount: 1u32.into(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: LpOwner::Paloma(Addr::unchecked("bob")),
+            zap: Some(ZapIn {
+                min_liquidity: Uint256::zero(),
+            }),
+            intent_id: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InsufficientLiquidityMinted { .. })
+    ));
+
+    Ok(())
+}
+
+/// Test that both legs of a deposit intent are minted together, bypassing the queue, and that
+/// a lone leg is refunded after expiry.
+#[test]
+fn deposit_intent() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    // A single-sided deposit waiting in the queue.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "dave", 1000)?;
+
+    let expiry = mock_env().block.time.seconds() + 100;
+    let mut intent_ids = vec![];
+    for _ in 0..2 {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("chris", &[]),
+            ExecuteMsg::RegisterDepositIntent {
+                pool_id: 0u32.into(),
+                amount0: 2000u32.into(),
+                amount1: 2000u32.into(),
+                sender0: DEPOSITOR0.to_string(),
+                sender1: DEPOSITOR0.to_string(),
+                tolerance_bps: 100,
+                expiry,
+            },
+        )?;
+        intent_ids.push(from_binary::<u64>(&r.data.unwrap())?);
+    }
+    assert_eq!(intent_ids, vec![0, 1]);
+
+    let leg =
+        |chain_id: Uint256, token: &str, amount: u32, intent_id: u64| ExecuteMsg::AddLiquidity {
+            pool_id: 0u32.into(),
+            chain_id,
+            token: token.to_string(),
+            amount: amount.into(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: LpOwner::Paloma(Addr::unchecked("ignored")),
+            zap: None,
+            intent_id: Some(intent_id),
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        };
+    // Legs which don't fit an intent are refunded.
+    for msg in [
+        leg(chains.0, TOKEN0, 1999, 0),
+        leg(chains.0, TOKEN0, 2000, 7),
+    ] {
+        let r = execute(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg)?;
+        assert_eq!(r.messages.len(), 1);
+    }
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        leg(chains.0, TOKEN0, 2000, 0),
+    )?;
+    assert_eq!(r.messages.len(), 0);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        leg(chains.0, TOKEN0, 2000, 0),
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    for msg in [
+        leg(chains.1, TOKEN1, 2000, 0),
+        leg(chains.0, TOKEN0, 2000, 1),
+    ] {
+        let r = execute(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg)?;
+        assert_eq!(r.messages.len(), 0);
+    }
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(12000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(12000u32));
+    assert_eq!(pool_info.pending_amount1, Uint256::from(1000u32));
+    assert_eq!(
+        LIQUIDITY.load(
+            &deps.storage,
+            (Uint256::zero().to_be_bytes().as_slice(), b"chris")
+        )?,
+        Uint256::from(2000u32)
+    );
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::RefundDepositIntent { intent_id: 1 },
+    );
+    assert!(matches!(r, Err(ContractError::IntentNotExpired { .. })));
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let r = execute(
+        deps.as_mut(),
+        env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::RefundDepositIntent { intent_id: 1 },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+
+    Ok(())
+}
+
+/// Test that intent legs only count from their registered senders, and that the part of a leg
+/// above the pool ratio is refunded.
+#[test]
+fn deposit_intent_legs() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("chris", &[]),
+        ExecuteMsg::RegisterDepositIntent {
+            pool_id: 0u32.into(),
+            amount0: 2000u32.into(),
+            amount1: 3000u32.into(),
+            sender0: DEPOSITOR0.to_string(),
+            sender1: DEPOSITOR1.to_string(),
+            tolerance_bps: 100,
+            expiry: mock_env().block.time.seconds() + 100,
+        },
+    )?;
+
+    let leg =
+        |chain_id: Uint256, token: &str, amount: u32, sender: &str| ExecuteMsg::AddLiquidity {
+            pool_id: 0u32.into(),
+            chain_id,
+            token: token.to_string(),
+            amount: amount.into(),
+            sender: sender.to_string(),
+            receiver: LpOwner::Paloma(Addr::unchecked("ignored")),
+            zap: None,
+            intent_id: Some(0),
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        };
+    // A leg from anyone else is refunded without taking the intent's slot.
+    for (msg, refunds) in [
+        (leg(chains.0, TOKEN0, 2000, DEPOSITOR1), 1),
+        (leg(chains.0, TOKEN0, 2000, DEPOSITOR0), 0),
+        (leg(chains.1, TOKEN1, 3000, DEPOSITOR1), 1),
+    ] {
+        let r = execute(deps.as_mut(), mock_env(), mock_info("relayer", &[]), msg)?;
+        assert_eq!(r.messages.len(), refunds);
+    }
+
+    // Only 2000 of the 3000 token1 fit the pool ratio; the other 1000 are refunded.
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(12000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(12000u32));
+    assert_eq!(
+        LIQUIDITY.load(
+            &deps.storage,
+            (Uint256::zero().to_be_bytes().as_slice(), b"chris")
+        )?,
+        Uint256::from(2000u32)
+    );
+
+    Ok(())
+}
+
+/// Test that a counter-side deposit is split across queued deposits per the pool's matching
+/// policy.
+#[test]
+fn matching_policy() -> Result<(), ContractError> {
+    for (policy, carol, dave) in [
+        (None, 1000u32, 0u32),
+        (Some(MatchingPolicy::ProRata), 750, 250),
+        (
+            Some(MatchingPolicy::RoundRobin { cap: 600u32.into() }),
+            600,
+            400,
+        ),
+    ] {
+        let mut deps = mock_dependencies();
+        let chains = setup(deps.as_mut())?;
+        let mut msg = create_pool_msg(chains, None);
+        if let ExecuteMsg::CreatePool {
+            matching_policy, ..
+        } = &mut msg
+        {
+            *matching_policy = policy;
+        }
+        execute(deps.as_mut(), mock_env(), mock_info("admin0000", &[]), msg)?;
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+        deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "carol", 3000)?;
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "dave", 1000)?;
+        deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "erin", 2000)?;
+
+        let pool_info: PoolInfo = from_binary(&query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Pool {
+                pool_id: 0u32.into(),
+            },
+        )?)?;
+        assert_eq!(pool_info.amount0, Uint256::from(12000u32));
+        assert_eq!(pool_info.pending_amount0, Uint256::from(2000u32));
+        let pool_id_key = Uint256::zero().to_be_bytes();
+        for (holder, liquidity) in [("carol", carol), ("dave", dave), ("erin", 1000)] {
+            assert_eq!(
+                LIQUIDITY
+                    .may_load(&deps.storage, (pool_id_key.as_slice(), holder.as_bytes()))?
+                    .unwrap_or_default(),
+                Uint256::from(liquidity)
+            );
+        }
+    }
+
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let mut msg = create_pool_msg(chains, None);
+    if let ExecuteMsg::CreatePool {
+        matching_policy, ..
+    } = &mut msg
+    {
+        *matching_policy = Some(MatchingPolicy::RoundRobin {
+            cap: Uint256::zero(),
+        });
+    }
+    let r = execute(deps.as_mut(), mock_env(), mock_info("admin0000", &[]), msg);
+    assert!(matches!(r, Err(ContractError::InvalidMatchingPolicy {})));
+
+    Ok(())
+}
+
+/// Test that matched deposits are removed from the liquidity queue in each matching branch, and
+/// that owners can cancel their queued deposits.
+#[test]
+fn liquidity_queue() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    let queue = |deps: Deps| -> Result<Vec<(u64, String, Uint256)>, ContractError> {
+        let r: LiquidityQueueResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::LiquidityQueue {
+                pool_id: 0u32.into(),
+                start_after: None,
+                limit: None,
+            },
+        )?)?;
+        Ok(r.deposits
+            .into_iter()
+            .map(|d| (d.seq, d.deposit.receiver.to_string(), d.deposit.amount))
+            .collect())
+    };
+
+    // Bootstrapping drains the queue.
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    assert_eq!(
+        queue(deps.as_ref())?,
+        vec![(0, "alice".to_string(), 10000u32.into())]
+    );
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    assert!(queue(deps.as_ref())?.is_empty());
+
+    // Same-side deposits are appended.
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "carol", 1000)?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "dave", 1000)?;
+    // A smaller counter deposit partially fills the head.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "erin", 500)?;
+    assert_eq!(
+        queue(deps.as_ref())?,
+        vec![
+            (1, "carol".to_string(), 500u32.into()),
+            (2, "dave".to_string(), 1000u32.into())
+        ]
+    );
+    // An equal counter deposit removes the head.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "frank", 500)?;
+    assert_eq!(
+        queue(deps.as_ref())?,
+        vec![(2, "dave".to_string(), 1000u32.into())]
+    );
+    // A larger counter deposit drains the queue and queues its remainder.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "gina", 1500)?;
+    assert_eq!(
+        queue(deps.as_ref())?,
+        vec![(3, "gina".to_string(), 500u32.into())]
+    );
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("dave", &[]),
+        ExecuteMsg::CancelQueuedDeposits {
+            pool_id: 0u32.into(),
+        },
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::QueuedDepositNotFound { .. })
+    ));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("gina", &[]),
+        ExecuteMsg::CancelQueuedDeposits {
+            pool_id: 0u32.into(),
+        },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    assert!(queue(deps.as_ref())?.is_empty());
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(pool_info.pending_amount0.is_zero());
+    assert!(pool_info.pending_amount1.is_zero());
+    assert_eq!(pool_info.amount0, Uint256::from(12000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(12000u32));
+
+    Ok(())
+}
+
+/// Test that a counter-side deposit matches a bounded batch of the queue per call, and that the
+/// rest is matched with `ContinueMatching`.
+#[test]
+fn continue_matching() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    for _ in 0..25 {
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "carol", 100)?;
+    }
+
+    // The first 20 queued deposits are matched, leaving 1000 token1 in progress.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "bob", 3000)?;
+    let matching: Option<LiquidityQueueElement> = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Matching {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(matching.unwrap().amount, Uint256::from(1000u32));
+    // Deposits keep arriving behind it: derek's waits for bob's, erin's joins the queue.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "derek", 100)?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "erin", 200)?;
+
+    // Anyone can match the rest, which queues the unmatched 300 token1, and then queue derek's.
+    for _ in 0..2 {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::ContinueMatching {
+                pool_id: 0u32.into(),
+            },
+        )?;
+    }
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::ContinueMatching {
+            pool_id: 0u32.into(),
+        },
+    );
+    assert!(matches!(r, Err(ContractError::NoMatchingInProgress { .. })));
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(12700u32));
+    assert_eq!(pool_info.amount1, Uint256::from(12700u32));
+    assert!(pool_info.pending_amount0.is_zero());
+    assert_eq!(pool_info.pending_amount1, Uint256::from(400u32));
+    for (holder, liquidity) in [(b"carol".as_slice(), 1250u32), (b"erin", 100)] {
+        assert_eq!(
+            LIQUIDITY.load(
+                &deps.storage,
+                (Uint256::zero().to_be_bytes().as_slice(), holder)
+            )?,
+            Uint256::from(liquidity)
+        );
+    }
+    let queue: LiquidityQueueResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityQueue {
+            pool_id: 0u32.into(),
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    let queued: Vec<_> = queue
+        .deposits
+        .iter()
+        .map(|queued| (queued.deposit.receiver.as_str(), queued.deposit.amount))
+        .collect();
+    assert_eq!(
+        queued,
+        [
+            ("bob", Uint256::from(300u32)),
+            ("derek", Uint256::from(100u32))
+        ]
+    );
+
+    Ok(())
+}
+
+/// Test that pro-rata shares span the whole queue when it takes more than one batch to match.
+#[test]
+fn pro_rata_continue_matching() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let mut msg = create_pool_msg(chains, None);
+    if let ExecuteMsg::CreatePool {
+        matching_policy, ..
+    } = &mut msg
+    {
+        *matching_policy = Some(MatchingPolicy::ProRata);
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("admin0000", &[]), msg)?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    for _ in 0..20 {
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "carol", 100)?;
+    }
+    for _ in 0..5 {
+        deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "dave", 100)?;
+    }
+
+    // Half the queue is matched: the first batch of 20 gets its half, leaving 250 token1 for the
+    // last 5 deposits.
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "bob", 1250)?;
+    let matching: Option<LiquidityQueueElement> = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Matching {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(matching.unwrap().amount, Uint256::from(250u32));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::ContinueMatching {
+            pool_id: 0u32.into(),
+        },
+    )?;
+
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(11250u32));
+    assert_eq!(pool_info.amount1, Uint256::from(11250u32));
+    assert_eq!(pool_info.pending_amount0, Uint256::from(1250u32));
+    assert!(pool_info.pending_amount1.is_zero());
+    for (holder, liquidity) in [(b"carol".as_slice(), 500u32), (b"dave", 125), (b"bob", 625)] {
+        assert_eq!(
+            LIQUIDITY.load(
+                &deps.storage,
+                (Uint256::zero().to_be_bytes().as_slice(), holder)
+            )?,
+            Uint256::from(liquidity)
+        );
+    }
+    let queue: LiquidityQueueResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityQueue {
+            pool_id: 0u32.into(),
+            start_after: None,
+            limit: Some(30),
+        },
+    )?)?;
+    assert_eq!(queue.deposits.len(), 25);
+    assert!(queue
+        .deposits
+        .iter()
+        .all(|queued| queued.deposit.amount == Uint256::from(50u32)));
+
+    Ok(())
+}
+
+/// Test that batched swaps clear at one uniform price once the window passes, refunding orders
+/// whose `min_amount_out` the price misses.
+#[test]
+fn batch_auction() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let mut msg = create_pool_msg(chains, None);
+    if let ExecuteMsg::CreatePool { batch_window, .. } = &mut msg {
+        *batch_window = Some(60);
+    }
+    execute(deps.as_mut(), mock_env(), mock_info("admin0000", &[]), msg)?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    let swap = |from_chain0: bool, min_amount_out: u32| {
+        let (chain_from_id, chain_to_id, token_from, token_to) = if from_chain0 {
+            (chains.0, chains.1, TOKEN0, TOKEN1)
+        } else {
+            (chains.1, chains.0, TOKEN1, TOKEN0)
+        };
+        ExecuteMsg::Swap {
+            chain_from_id,
+            chain_to_id,
+            token_from: token_from.to_string(),
+            token_to: token_to.to_string(),
+            sender: "0x3333333333333333333333333333333333333333".to_string(),
+            receiver: "0x4444444444444444444444444444444444444444".to_string(),
+            amount: 1000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: min_amount_out.into(),
+            },
+            queue_expiry: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        }
+    };
+    for (from_chain0, min_amount_out) in [(true, 990), (false, 0), (true, 950)] {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            swap(from_chain0, min_amount_out),
+        )?;
+        assert!(r.messages.is_empty());
+    }
+    let batch: Option<SwapBatch> = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SwapBatch {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(batch.unwrap().orders.len(), 3);
+
+    let settle = ExecuteMsg::SettleBatch {
+        pool_id: 0u32.into(),
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        settle.clone(),
+    );
+    assert!(matches!(r, Err(ContractError::BatchNotClosed { .. })));
+
+    // With all three orders the price gives 914 token1 per 1000 token0, missing both token0
+    // limits. The first order misses by more and is refunded, after which both remaining orders
+    // get 997.
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60);
+    let r = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("anyone", &[]),
+        settle.clone(),
+    )?;
+    assert_eq!(r.messages.len(), 3);
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(10003u32));
+    assert_eq!(pool_info.amount1, Uint256::from(10003u32));
+    let r = execute(deps.as_mut(), env, mock_info("anyone", &[]), settle);
+    assert!(matches!(r, Err(ContractError::BatchNotFound { .. })));
+
+    Ok(())
+}
+
+/// Test that exact-output swaps spend only the input needed on the curve and refund the rest.
+#[test]
+fn exact_out_swap() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    let swap = |amount: u32, amount_out: u32, max_amount_in: u32| ExecuteMsg::Swap {
+        chain_from_id: chains.0,
+        chain_to_id: chains.1,
+        token_from: TOKEN0.to_string(),
+        token_to: TOKEN1.to_string(),
+        sender: "0x3333333333333333333333333333333333333333".to_string(),
+        receiver: "0x4444444444444444444444444444444444444444".to_string(),
+        amount: amount.into(),
+        mode: SwapMode::ExactOut {
+            amount_out: amount_out.into(),
+            max_amount_in: max_amount_in.into(),
+        },
+        queue_expiry: None,
+        tx_hash: next_tx_hash(),
+        log_index: 0,
+    };
+    // 1000 token1 out costs 1115 token0.
+    for (amount, max_amount_in) in [(1500, 1100), (1100, 2000)] {
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            swap(amount, 1000, max_amount_in),
+        );
+        assert!(matches!(
+            r,
+            Err(ContractError::SlippageExceeded { amount, .. }) if amount == Uint256::from(1115u32)
+        ));
+    }
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(1500, 10000, 20000),
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InsufficientLiquidity { .. })
+    ));
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(1500, 1000, 2000),
+    )?;
+    // The swap payout and the refund of the unused 385 token0.
+    assert_eq!(r.messages.len(), 2);
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(11115u32));
+    assert_eq!(pool_info.amount1, Uint256::from(9000u32));
+
+    Ok(())
+}
+
+/// Test that swaps which cannot be filled wait in the swap queue, fill in order once liquidity
+/// arrives, and are refunded after expiry.
+#[test]
+fn swap_queue() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    let now = mock_env().block.time.seconds();
+    let swap = |amount: u32, mode: SwapMode, queue_expiry: u64| ExecuteMsg::Swap {
+        chain_from_id: chains.0,
+        chain_to_id: chains.1,
+        token_from: TOKEN0.to_string(),
+        token_to: TOKEN1.to_string(),
+        sender: "0x3333333333333333333333333333333333333333".to_string(),
+        receiver: "0x4444444444444444444444444444444444444444".to_string(),
+        amount: amount.into(),
+        mode,
+        queue_expiry: Some(queue_expiry),
+        tx_hash: next_tx_hash(),
+        log_index: 0,
+    };
+    // The whole token1 reserve cannot be bought yet.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(
+            25000,
+            SwapMode::ExactOut {
+                amount_out: 10000u32.into(),
+                max_amount_in: 25000u32.into(),
+            },
+            now + 1000,
+        ),
+    )?;
+    assert!(r.messages.is_empty());
+    assert_eq!(from_binary::<u64>(&r.data.unwrap())?, 0);
+    // A swap missing its slippage limit fails rather than waiting.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(
+            1000,
+            SwapMode::ExactIn {
+                min_amount_out: 5000u32.into(),
+            },
+            now + 100,
+        ),
+    );
+    assert!(matches!(r, Err(ContractError::SlippageExceeded { .. })));
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(
+            1000,
+            SwapMode::ExactOut {
+                amount_out: 10000u32.into(),
+                max_amount_in: 1000u32.into(),
+            },
+            now + 100,
+        ),
+    )?;
+    let queued: QueuedSwapResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::QueuedSwap {
+            pool_id: 0u32.into(),
+            seq: 1,
+        },
+    )?)?;
+    assert_eq!((queued.position, queued.depth), (1, 2));
+
+    // Once the reserves double, the first swap costs 20061 token0 and 4939 are refunded. The
+    // second swap still cannot buy the whole token1 reserve.
+    let r = deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "bob", 10000)?;
+    assert!(r.messages.is_empty());
+    let r = deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "bob", 10000)?;
+    assert_eq!(r.messages.len(), 2);
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert_eq!(pool_info.amount0, Uint256::from(40061u32));
+    assert_eq!(pool_info.amount1, Uint256::from(10000u32));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let r = execute(
+        deps.as_mut(),
+        env,
+        mock_info("anyone", &[]),
+        ExecuteMsg::ProcessSwapQueue {
+            pool_id: 0u32.into(),
+        },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    let queue: SwapQueueResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SwapQueue {
+            pool_id: 0u32.into(),
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    assert_eq!(queue.depth, 0);
+
+    Ok(())
+}
+
+/// Test queueing swaps against a pool which is not bootstrapped yet, and against reserves tied up
+/// in a pending withdrawal.
+#[test]
+fn swap_queue_liquidity() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    let now = mock_env().block.time.seconds();
+    let swap = |mode: SwapMode, queue_expiry: Option<u64>| ExecuteMsg::Swap {
+        chain_from_id: chains.0,
+        chain_to_id: chains.1,
+        token_from: TOKEN0.to_string(),
+        token_to: TOKEN1.to_string(),
+        sender: DEPOSITOR0.to_string(),
+        receiver: DEPOSITOR1.to_string(),
+        amount: 20000u32.into(),
+        mode,
+        queue_expiry,
+        tx_hash: next_tx_hash(),
+        log_index: 0,
+    };
+    let depth = |deps: Deps| -> Result<u64, ContractError> {
+        let queue: SwapQueueResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::SwapQueue {
+                pool_id: 0u32.into(),
+                start_after: None,
+                limit: None,
+            },
+        )?)?;
+        Ok(queue.depth)
+    };
+    let exact_in = SwapMode::ExactIn {
+        min_amount_out: Uint256::zero(),
+    };
+
+    // An empty pool fails the swap unless it may wait, and fills it once bootstrapped.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(exact_in.clone(), None),
+    );
+    assert!(matches!(r, Err(ContractError::EmptyPool { .. })));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(exact_in, Some(now + 1000)),
+    )?;
+    assert!(r.messages.is_empty());
+    assert_eq!(depth(deps.as_ref())?, 1);
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    let r = deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    assert_eq!(r.messages.len(), 1);
+    assert_eq!(depth(deps.as_ref())?, 0);
+
+    // A withdrawal ties up most of the token1 reserve, which returns when it fails.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveLiquidity {
+            pool_id: 0u32.into(),
+            amount: 8000u32.into(),
+            receiver0: DEPOSITOR0.to_string(),
+            receiver1: DEPOSITOR1.to_string(),
+            min_amount0: Uint256::zero(),
+            min_amount1: Uint256::zero(),
+            deadline: now,
+        },
+    )?;
+    assert_eq!(r.messages.len(), 2);
+    let withdrawal1: PendingSettlementsResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::PendingSettlements {
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    let withdrawal1 = withdrawal1.settlements.last().unwrap().clone();
+    assert_eq!(withdrawal1.chain_id, chains.1);
+    let exact_out = SwapMode::ExactOut {
+        amount_out: 1000u32.into(),
+        max_amount_in: 20000u32.into(),
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        swap(exact_out, Some(now + 1000)),
+    )?;
+    assert!(r.messages.is_empty());
+    assert_eq!(depth(deps.as_ref())?, 1);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::FailSettlement {
+            nonce: withdrawal1.nonce,
+        },
+    )?;
+    // The queued swap pays out and refunds its unused input.
+    assert_eq!(r.messages.len(), 2);
+    assert_eq!(depth(deps.as_ref())?, 0);
+
+    Ok(())
+}
+
+/// Test acknowledging payouts and rolling back failed withdrawals and swaps.
+#[test]
+fn settlement() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let admin = mock_info("admin0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin,
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 20000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 5000)?;
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveLiquidity {
+            pool_id: 0u32.into(),
+            amount: 4500u32.into(),
+            receiver0: DEPOSITOR0.to_string(),
+            receiver1: DEPOSITOR1.to_string(),
+            min_amount0: Uint256::zero(),
+            min_amount1: Uint256::zero(),
+            deadline: mock_env().block.time.seconds(),
+        },
+    )?;
+    assert_eq!(r.messages.len(), 2);
+    let pending = |deps: Deps| -> Result<Vec<u64>, ContractError> {
+        let r: PendingSettlementsResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::PendingSettlements {
+                start_after: None,
+                limit: None,
+            },
+        )?)?;
+        Ok(r.settlements.iter().map(|s| s.nonce).collect())
+    };
+    assert_eq!(pending(deps.as_ref())?, [0, 1]);
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::AckSettlement { nonce: 0 },
+    );
+    assert!(matches!(r, Err(ContractError::Unauthorized { .. })));
+    let relayer = mock_info("relayer", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer.clone(),
+        ExecuteMsg::AckSettlement { nonce: 0 },
+    )?;
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer.clone(),
+        ExecuteMsg::AckSettlement { nonce: 0 },
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::SettlementNotFound { nonce: 0 })
+    ));
+
+    // The failed target leg returns to the reserve along with its share of the burned liquidity.
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer.clone(),
+        ExecuteMsg::FailSettlement { nonce: 1 },
+    )?;
+    assert_eq!(r.messages.len(), 0);
+    let pool = |deps: Deps| -> Result<PoolInfo, ContractError> {
+        Ok(from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::Pool {
+                pool_id: 0u32.into(),
+            },
+        )?)?)
+    };
+    let pool_info = pool(deps.as_ref())?;
+    assert_eq!(pool_info.amount0, Uint256::from(11000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(5000u32));
+    assert_eq!(pool_info.total_liquidity, Uint256::from(7750u32));
+    let invariant: LiquidityInvariantResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityInvariant {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(invariant.holds);
+
+    // A failed swap is reversed and its input refunded as a new payout.
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::Swap {
+            chain_from_id: chains.0,
+            chain_to_id: chains.1,
+            token_from: TOKEN0.to_string(),
+            token_to: TOKEN1.to_string(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: DEPOSITOR1.to_string(),
+            amount: 1000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: Uint256::zero(),
+            },
+            queue_expiry: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    )?;
+    assert_eq!(pending(deps.as_ref())?, [2]);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer,
+        ExecuteMsg::FailSettlement { nonce: 2 },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    let pool_info = pool(deps.as_ref())?;
+    assert_eq!(pool_info.amount0, Uint256::from(11000u32));
+    assert_eq!(pool_info.amount1, Uint256::from(5000u32));
+    let settlement: Option<Settlement> = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Settlement { nonce: 3 },
+    )?)?;
+    let settlement = settlement.unwrap();
+    assert_eq!(settlement.kind, PayoutKind::Refund);
+    assert_eq!(settlement.amount, Uint256::from(1000u32));
+    assert_eq!(settlement.recipient, DEPOSITOR0);
+
+    // A leg rounding down to nothing carries no shares, so failing it restores none.
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveLiquidity {
+            pool_id: 0u32.into(),
+            amount: 1u32.into(),
+            receiver0: DEPOSITOR0.to_string(),
+            receiver1: DEPOSITOR1.to_string(),
+            min_amount0: Uint256::zero(),
+            min_amount1: Uint256::zero(),
+            deadline: mock_env().block.time.seconds(),
+        },
+    )?;
+    assert_eq!(pending(deps.as_ref())?, [3, 4, 5]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::FailSettlement { nonce: 5 },
+    )?;
+    let pool_info = pool(deps.as_ref())?;
+    assert_eq!(pool_info.amount0, Uint256::from(10999u32));
+    assert_eq!(pool_info.amount1, Uint256::from(5000u32));
+    assert_eq!(pool_info.total_liquidity, Uint256::from(7749u32));
+
+    Ok(())
+}
+
+/// Test that a swap can still be failed after withdrawals took part of its input, refunding what
+/// is left of it.
+#[test]
+fn fail_settlement_after_withdrawal() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+    let relayer = mock_info("relayer", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer.clone(),
+        ExecuteMsg::Swap {
+            chain_from_id: chains.0,
+            chain_to_id: chains.1,
+            token_from: TOKEN0.to_string(),
+            token_to: TOKEN1.to_string(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: DEPOSITOR1.to_string(),
+            amount: 10000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: Uint256::zero(),
+            },
+            queue_expiry: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    )?;
+    // Alice withdraws 90% of the pool, including most of the swap's input.
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveLiquidity {
+            pool_id: 0u32.into(),
+            amount: 9000u32.into(),
+            receiver0: DEPOSITOR0.to_string(),
+            receiver1: DEPOSITOR1.to_string(),
+            min_amount0: Uint256::zero(),
+            min_amount1: Uint256::zero(),
+            deadline: mock_env().block.time.seconds(),
+        },
+    )?;
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        relayer,
+        ExecuteMsg::FailSettlement { nonce: 0 },
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    let pool_info: PoolInfo = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Pool {
+            pool_id: 0u32.into(),
+        },
+    )?)?;
+    assert!(pool_info.amount0.is_zero());
+    assert_eq!(pool_info.amount1, Uint256::from(5493u32));
+    let settlement: Option<Settlement> = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Settlement { nonce: 3 },
+    )?)?;
+    let settlement = settlement.unwrap();
+    assert_eq!(settlement.kind, PayoutKind::Refund);
+    assert_eq!(settlement.amount, Uint256::from(2000u32));
+    assert_eq!(settlement.recipient, DEPOSITOR0);
+
+    Ok(())
+}
+
+/// Test rejecting a deposit relayed twice, or by anyone but the relayer.
+#[test]
+fn replay_protection() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    let tx_hash = "0xabcdef".to_string();
+    let add = |tx_hash: &str, log_index: u64| ExecuteMsg::AddLiquidity {
+        pool_id: 0u32.into(),
+        chain_id: chains.0,
+        token: TOKEN0.to_string(),
+        amount: 1000u32.into(),
+        sender: DEPOSITOR0.to_string(),
+        receiver: LpOwner::Paloma(Addr::unchecked("alice")),
+        zap: None,
+        intent_id: None,
+        tx_hash: tx_hash.to_string(),
+        log_index,
+    };
+    let processed = |deps: Deps, chain_id: Uint256| -> Result<bool, ContractError> {
+        Ok(from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::DepositProcessed {
+                chain_id,
+                tx_hash: tx_hash.clone(),
+                log_index: 0,
+            },
+        )?)?)
+    };
+    assert!(!processed(deps.as_ref(), chains.0)?);
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        add(&tx_hash, 0),
+    );
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+    assert!(!processed(deps.as_ref(), chains.0)?);
+
+    let relayer = mock_info("relayer", &[]);
+    execute(deps.as_mut(), mock_env(), relayer.clone(), add(&tx_hash, 0))?;
+    assert!(processed(deps.as_ref(), chains.0)?);
+    assert!(!processed(deps.as_ref(), chains.1)?);
+    for tx_hash in [tx_hash.as_str(), "0xABCDEF"] {
+        let r = execute(deps.as_mut(), mock_env(), relayer.clone(), add(tx_hash, 0));
+        assert!(matches!(
+            r,
+            Err(ContractError::DuplicateDeposit { log_index: 0, .. })
+        ));
+    }
+    execute(deps.as_mut(), mock_env(), relayer, add(&tx_hash, 1))?;
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        ExecuteMsg::Swap {
+            chain_from_id: chains.0,
+            chain_to_id: chains.1,
+            token_from: TOKEN0.to_string(),
+            token_to: TOKEN1.to_string(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: DEPOSITOR1.to_string(),
+            amount: 1000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: Uint256::zero(),
+            },
+            queue_expiry: None,
+            tx_hash,
+            log_index: 1,
+        },
+    );
+    assert!(matches!(r, Err(ContractError::DuplicateDeposit { .. })));
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("anyone", &[]),
+        ExecuteMsg::Swap {
+            chain_from_id: chains.0,
+            chain_to_id: chains.1,
+            token_from: TOKEN0.to_string(),
+            token_to: TOKEN1.to_string(),
+            sender: DEPOSITOR0.to_string(),
+            receiver: DEPOSITOR1.to_string(),
+            amount: 1000u32.into(),
+            mode: SwapMode::ExactIn {
+                min_amount_out: Uint256::zero(),
+            },
+            queue_expiry: None,
+            tx_hash: next_tx_hash(),
+            log_index: 0,
+        },
+    );
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+
+    Ok(())
+}
+
+/// Test LP shares owned by remote accounts and removed through relayed requests.
+#[test]
+fn remote_lp_owner() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let admin = mock_info("admin0000", &[]);
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin,
+        create_pool_msg(chains, None),
+    )?;
+    let owner = |address: &str| LpOwner::Remote {
+        chain_id: chains.0,
+        address: address.to_string(),
+    };
+    for (chain_id, token, sender, amount) in [
+        (chains.0, TOKEN0, DEPOSITOR0, 20000u32),
+        (chains.1, TOKEN1, DEPOSITOR1, 5000u32),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::AddLiquidity {
+                pool_id: 0u32.into(),
+                chain_id,
+                token: token.to_string(),
+                amount: amount.into(),
+                sender: sender.to_string(),
+                receiver: owner(DEPOSITOR0),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+    }
+    // Paloma owners can't pose as a remote or locked holder.
+    let remote_holder = format!("{}/{}", chains.0, DEPOSITOR0.to_lowercase());
+    for receiver in [LOCKED_LIQUIDITY_HOLDER, &remote_holder, "Alice"] {
+        let r = deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, receiver, 1000);
+        assert!(matches!(r, Err(ContractError::InvalidAddress { .. })));
+    }
+    let liquidity = |deps: Deps, owner: LpOwner| -> Result<Uint256, ContractError> {
+        Ok(from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::Liquidity {
+                pool_id: 0u32.into(),
+                owner,
+            },
+        )?)?)
+    };
+    assert_eq!(
+        liquidity(deps.as_ref(), owner(DEPOSITOR0))?,
+        Uint256::from(9000u32)
+    );
+    assert_eq!(
+        liquidity(deps.as_ref(), owner(&DEPOSITOR0.to_uppercase()[2..]))?,
+        Uint256::from(9000u32)
+    );
+    assert!(liquidity(deps.as_ref(), LpOwner::Paloma(Addr::unchecked(DEPOSITOR0)))?.is_zero());
+
+    let tx_hash = next_tx_hash();
+    let remove = |amount: u32| ExecuteMsg::RelayedRemoveLiquidity {
+        chain_id: chains.0,
+        sender: DEPOSITOR0.to_string(),
+        tx_hash: tx_hash.clone(),
+        log_index: 0,
+        pool_id: 0u32.into(),
+        amount: amount.into(),
+        receiver0: DEPOSITOR0.to_string(),
+        receiver1: DEPOSITOR1.to_string(),
+        min_amount0: Uint256::zero(),
+        min_amount1: Uint256::zero(),
+        deadline: mock_env().block.time.seconds(),
+    };
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info(DEPOSITOR0, &[]),
+        remove(4500),
+    );
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        remove(4500),
+    )?;
+    assert_eq!(r.messages.len(), 2);
+    assert_eq!(
+        liquidity(deps.as_ref(), owner(DEPOSITOR0))?,
+        Uint256::from(4500u32)
+    );
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        remove(4500),
+    );
+    assert!(matches!(r, Err(ContractError::DuplicateDeposit { .. })));
+
+    Ok(())
+}
+
+/// Test EIP-712 intents signed by remote owners and submitted by anyone.
+#[test]
+fn signed_intent() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    let key = SigningKey::from_bytes(&[7u8; 32]).unwrap();
+    let point = key.verifying_key().to_encoded_point(false);
+    let signer = format!(
+        "0x{}",
+        Keccak256::digest(&point.as_bytes()[1..])[12..]
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+    );
+    let owner = LpOwner::Remote {
+        chain_id: chains.0,
+        address: signer.clone(),
+    };
+    for (chain_id, token, sender, amount) in [
+        (chains.0, TOKEN0, DEPOSITOR0, 20000u32),
+        (chains.1, TOKEN1, DEPOSITOR1, 5000u32),
+        (chains.0, TOKEN0, DEPOSITOR0, 1000u32),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::AddLiquidity {
+                pool_id: 0u32.into(),
+                chain_id,
+                token: token.to_string(),
+                amount: amount.into(),
+                sender: sender.to_string(),
+                receiver: owner.clone(),
+                zap: None,
+                intent_id: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+    }
+
+    let now = mock_env().block.time.seconds();
+    let remove = |amount: u32| SignedAction::RemoveLiquidity {
+        pool_id: 0u32.into(),
+        amount: amount.into(),
+        receiver0: DEPOSITOR0.to_string(),
+        receiver1: DEPOSITOR1.to_string(),
+        min_amount0: Uint256::zero(),
+        min_amount1: Uint256::zero(),
+    };
+    let sign = |key: &SigningKey, action: &SignedAction, nonce: u64, deadline: u64| {
+        let digest = eip712::digest(
+            mock_env().contract.address.as_str(),
+            chains.0,
+            action,
+            nonce,
+            deadline,
+        );
+        let signature: recoverable::Signature = key.sign_prehash(&digest).unwrap();
+        let mut signature = signature.as_ref().to_vec();
+        signature[64] += 27;
+        Binary(signature)
+    };
+    let submit = |deps: DepsMut, action: SignedAction, nonce, deadline, signature| {
+        execute(
+            deps,
+            mock_env(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::SubmitSignedIntent {
+                chain_id: chains.0,
+                action,
+                nonce,
+                deadline,
+                signature,
+            },
+        )
+    };
+
+    let r = submit(deps.as_mut(), remove(4500), 0, now, Binary(vec![0u8; 64]));
+    assert!(matches!(r, Err(ContractError::InvalidSignature {})));
+    let r = submit(
+        deps.as_mut(),
+        remove(4500),
+        0,
+        now - 1,
+        sign(&key, &remove(4500), 0, now - 1),
+    );
+    assert!(matches!(r, Err(ContractError::Expired { .. })));
+    let r = submit(
+        deps.as_mut(),
+        remove(4500),
+        1,
+        now,
+        sign(&key, &remove(4500), 1, now),
+    );
+    assert!(matches!(
+        r,
+        Err(ContractError::InvalidNonce {
+            nonce: 1,
+            expected: 0
+        })
+    ));
+    // A tampered action or another key recovers a signer without shares.
+    let other = SigningKey::from_bytes(&[8u8; 32]).unwrap();
+    for signature in [
+        sign(&key, &remove(1), 0, now),
+        sign(&other, &remove(4500), 0, now),
+    ] {
+        let r = submit(deps.as_mut(), remove(4500), 0, now, signature);
+        assert!(matches!(
+            r,
+            Err(ContractError::InsufficientLiquidity { .. })
+        ));
+    }
+
+    let signature = sign(&key, &remove(4500), 0, now);
+    let r = submit(deps.as_mut(), remove(4500), 0, now, signature.clone())?;
+    assert_eq!(r.messages.len(), 2);
+    let r = submit(deps.as_mut(), remove(4500), 0, now, signature);
+    assert!(matches!(r, Err(ContractError::InvalidNonce { .. })));
+    let nonce: u64 = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::SignerNonce {
+            owner: owner.clone(),
+        },
+    )?)?;
+    assert_eq!(nonce, 1);
+    let liquidity: Uint256 = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::Liquidity {
+            pool_id: 0u32.into(),
+            owner,
+        },
+    )?)?;
+    assert_eq!(liquidity, Uint256::from(4500u32));
+
+    let cancel = SignedAction::CancelQueuedDeposits {
+        pool_id: 0u32.into(),
+    };
+    let r = submit(
+        deps.as_mut(),
+        cancel.clone(),
+        1,
+        now,
+        sign(&key, &cancel, 1, now),
+    )?;
+    assert_eq!(r.messages.len(), 1);
+
+    Ok(())
+}
+
+/// Test collecting payouts in an outbox and sending them as batches aggregated by recipient.
+#[test]
+fn outbox() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let admin = mock_info("admin0000", &[]);
+    for msg in [
+        create_pool_msg(chains, None),
+        ExecuteMsg::SetBatchSize {
+            chain_id: chains.1,
+            batch_size: Some(3),
+        },
+    ] {
+        execute(deps.as_mut(), mock_env(), admin.clone(), msg)?;
+    }
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 20000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 5000)?;
+    let swap = |deps: DepsMut, to_chain1: bool, receiver: &str| {
+        let (chain_from_id, chain_to_id, token_from, token_to) = if to_chain1 {
+            (chains.0, chains.1, TOKEN0, TOKEN1)
+        } else {
+            (chains.1, chains.0, TOKEN1, TOKEN0)
+        };
+        execute(
+            deps,
+            mock_env(),
+            mock_info("relayer", &[]),
+            ExecuteMsg::Swap {
+                chain_from_id,
+                chain_to_id,
+                token_from: token_from.to_string(),
+                token_to: token_to.to_string(),
+                sender: DEPOSITOR0.to_string(),
+                receiver: receiver.to_string(),
+                amount: 1000u32.into(),
+                mode: SwapMode::ExactIn {
+                    min_amount_out: Uint256::zero(),
+                },
+                queue_expiry: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )
+    };
+    let outbox = |deps: Deps, chain_id: Uint256| -> Result<Vec<Settlement>, ContractError> {
+        Ok(from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::Outbox {
+                chain_id,
+                pool_id: 0u32.into(),
+            },
+        )?)?)
+    };
+    let pending = |deps: Deps| -> Result<Vec<Settlement>, ContractError> {
+        let r: PendingSettlementsResponse = from_binary(&query(
+            deps,
+            mock_env(),
+            QueryMsg::PendingSettlements {
+                start_after: None,
+                limit: None,
+            },
+        )?)?;
+        Ok(r.settlements)
+    };
+    let abi = ethabi::Contract::load(include_bytes!("../abi/factory.json").as_slice()).unwrap();
+    let function = abi.function("batch_transfer").unwrap();
+    // The target chain, recipients and amounts of a batch.
+    let batch = |r: &Response<PalomaMsg>| {
+        let (job_id, payload) = match &r.messages[0].msg {
+            CosmosMsg::Custom(PalomaMsg { job_id, payload }) => (job_id, payload),
+            _ => panic!("expected a paloma message"),
+        };
+        assert_eq!(payload.0[..4], function.short_signature());
+        let tokens = function.decode_input(&payload.0[4..]).unwrap();
+        let recipients: Vec<String> = tokens[1]
+            .clone()
+            .into_array()
+            .unwrap()
+            .into_iter()
+            .map(|token| format!("{:#x}", token.into_address().unwrap()))
+            .collect();
+        let amounts: Vec<Uint256> = tokens[2]
+            .clone()
+            .into_array()
+            .unwrap()
+            .into_iter()
+            .map(|token| {
+                let mut word = [0u8; 32];
+                token.into_uint().unwrap().to_big_endian(&mut word);
+                Uint256::from_be_bytes(word)
+            })
+            .collect();
+        (job_id.clone(), recipients, amounts)
+    };
+    // The total paid to `recipient` on `chain_id` by `settlements`.
+    let paid = |settlements: &[Settlement], chain_id: Uint256, recipient: &str| -> Uint256 {
+        settlements
+            .iter()
+            .filter(|settlement| settlement.chain_id == chain_id)
+            .filter(|settlement| settlement.recipient == recipient)
+            .map(|settlement| settlement.amount)
+            .fold(Uint256::zero(), |total, amount| total + amount)
+    };
+
+    for receiver in [DEPOSITOR1, DEPOSITOR0] {
+        let r = swap(deps.as_mut(), true, receiver)?;
+        assert_eq!(r.messages.len(), 0);
+    }
+    let collected = outbox(deps.as_ref(), chains.1)?;
+    assert_eq!(collected.len(), 2);
+    let r = swap(deps.as_mut(), true, DEPOSITOR1)?;
+    assert_eq!(r.messages.len(), 1);
+    assert!(outbox(deps.as_ref(), chains.1)?.is_empty());
+    let settlements = pending(deps.as_ref())?;
+    assert_eq!(settlements.len(), 3);
+
+    // Payouts to DEPOSITOR1 are aggregated into one transfer.
+    let (job_id, recipients, amounts) = batch(&r);
+    assert_eq!(job_id, "wxyz");
+    assert_eq!(recipients, [DEPOSITOR1, DEPOSITOR0]);
+    assert_eq!(
+        amounts,
+        [
+            paid(&settlements, chains.1, DEPOSITOR1),
+            paid(&settlements, chains.1, DEPOSITOR0)
+        ]
+    );
+
+    swap(deps.as_mut(), true, DEPOSITOR0)?;
+    let flush = |chain_id| ExecuteMsg::FlushOutbox {
+        chain_id,
+        pool_id: 0u32.into(),
+    };
+    let r = execute(deps.as_mut(), mock_env(), admin.clone(), flush(chains.1));
+    assert!(matches!(r, Err(ContractError::Unauthorized {})));
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        flush(chains.1),
+    )?;
+    assert_eq!(r.messages.len(), 1);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("relayer", &[]),
+        flush(chains.1),
+    );
+    assert!(matches!(r, Err(ContractError::OutboxEmpty { .. })));
+
+    // With both chains batching, each token is collected in the outbox of its own chain.
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        admin,
+        ExecuteMsg::SetBatchSize {
+            chain_id: chains.0,
+            batch_size: Some(3),
+        },
+    )?;
+    let r = swap(deps.as_mut(), false, DEPOSITOR1)?;
+    assert_eq!(r.messages.len(), 0);
+    let r = swap(deps.as_mut(), true, DEPOSITOR1)?;
+    assert_eq!(r.messages.len(), 0);
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("alice", &[]),
+        ExecuteMsg::RemoveLiquidity {
+            pool_id: 0u32.into(),
+            amount: 1000u32.into(),
+            receiver0: DEPOSITOR1.to_string(),
+            receiver1: DEPOSITOR0.to_string(),
+            min_amount0: Uint256::zero(),
+            min_amount1: Uint256::zero(),
+            deadline: mock_env().block.time.seconds(),
+        },
+    )?;
+    assert_eq!(r.messages.len(), 0);
+    for chain_id in [chains.0, chains.1] {
+        let collected = outbox(deps.as_ref(), chain_id)?;
+        assert_eq!(collected.len(), 2);
+        assert!(collected
+            .iter()
+            .all(|settlement| settlement.chain_id == chain_id));
+    }
+    // Both payouts to DEPOSITOR1 on chain 0 are aggregated, while only the recipients on chain 1
+    // differ, and no batch carries payouts of the other chain.
+    let before = pending(deps.as_ref())?.len();
+    for (chain_id, factory, recipients) in [
+        (chains.0, "abcd", &[DEPOSITOR1][..]),
+        (chains.1, "wxyz", &[DEPOSITOR1, DEPOSITOR0][..]),
+    ] {
+        let collected = outbox(deps.as_ref(), chain_id)?;
+        let r = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("relayer", &[]),
+            flush(chain_id),
+        )?;
+        let (job_id, sent, amounts) = batch(&r);
+        assert_eq!(job_id, factory);
+        assert_eq!(sent, recipients);
+        let totals: Vec<Uint256> = recipients
+            .iter()
+            .map(|recipient| paid(&collected, chain_id, recipient))
+            .collect();
+        assert_eq!(amounts, totals);
+    }
+    assert_eq!(pending(deps.as_ref())?.len(), before + 4);
+
+    Ok(())
+}
+
+/// Test the nonce and deadline stamped on factory calls, counted separately for each chain.
+#[test]
+fn payload_nonces() -> Result<(), ContractError> {
+    use ethabi::Token;
+
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    let abi = ethabi::Contract::load(include_bytes!("../abi/factory.json").as_slice()).unwrap();
+    // The target chain, nonce and deadline of a factory call.
+    let stamp = |r: &Response<PalomaMsg>| {
+        let (job_id, payload) = match &r.messages.last().unwrap().msg {
+            CosmosMsg::Custom(PalomaMsg { job_id, payload }) => (job_id, payload),
+            _ => panic!("expected a paloma message"),
+        };
+        let function = abi
+            .functions()
+            .find(|function| function.short_signature() == payload.0[..4])
+            .unwrap();
+        let tokens = function.decode_input(&payload.0[4..]).unwrap();
+        let (nonce, deadline) = match tokens.as_slice() {
+            [.., Token::Uint(nonce), Token::Uint(deadline)] => (nonce, deadline),
+            _ => panic!("expected a nonce and a deadline"),
+        };
+        (job_id.clone(), nonce.as_u64(), deadline.as_u64())
+    };
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    let start = mock_env().block.time.seconds();
+    assert_eq!(stamp(&r), ("wxyz".to_string(), 0, start + 3600));
+    deposit(deps.as_mut(), chains.0, TOKEN0, DEPOSITOR0, "alice", 10000)?;
+    deposit(deps.as_mut(), chains.1, TOKEN1, DEPOSITOR1, "alice", 10000)?;
+
+    for (delay, (chain_from_id, chain_to_id), token_from, token_to, expected) in [
+        (100, chains, TOKEN0, TOKEN1, ("wxyz", 1)),
+        (200, chains, TOKEN0, TOKEN1, ("wxyz", 2)),
+        (300, (chains.1, chains.0), TOKEN1, TOKEN0, ("abcd", 1)),
+        (400, chains, TOKEN0, TOKEN1, ("wxyz", 3)),
+    ] {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(delay);
+        let r = execute(
+            deps.as_mut(),
+            env,
+            mock_info("relayer", &[]),
+            ExecuteMsg::Swap {
+                chain_from_id,
+                chain_to_id,
+                token_from: token_from.to_string(),
+                token_to: token_to.to_string(),
+                sender: DEPOSITOR0.to_string(),
+                receiver: DEPOSITOR1.to_string(),
+                amount: 100u32.into(),
+                mode: SwapMode::ExactIn {
+                    min_amount_out: Uint256::zero(),
+                },
+                queue_expiry: None,
+                tx_hash: next_tx_hash(),
+                log_index: 0,
+            },
+        )?;
+        assert_eq!(
+            stamp(&r),
+            (expected.0.to_string(), expected.1, start + delay + 3600)
+        );
+    }
+
+    Ok(())
+}
+
+/// Test factory call encodings against known Solidity encodings.
+#[test]
+fn abi_encoding() -> Result<(), ContractError> {
+    let hex = |data: Vec<u8>| data.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    let envelope = Envelope {
+        nonce: 2,
+        deadline: Timestamp::from_seconds(3),
+    };
+    assert_eq!(
+        hex(EVM.create_pool(TOKEN0, 1u8.into(), &envelope)?),
+        concat!(
+            "3d72dca8",
+            "0000000000000000000000000123456789012345678901234567890123456789",
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "0000000000000000000000000000000000000000000000000000000000000002",
+            "0000000000000000000000000000000000000000000000000000000000000003",
+        )
+    );
+
+    let envelope = Envelope {
+        nonce: 5,
+        deadline: Timestamp::from_seconds(1_700_000_000),
+    };
+    let args = (Uint256::from(7u8), Uint256::from(9000u32), DEPOSITOR0);
+    let body = concat!(
+        "0000000000000000000000000000000000000000000000000000000000000007",
+        "0000000000000000000000000000000000000000000000000000000000002328",
+        "0000000000000000000000001111111111111111111111111111111111111111",
+        "0000000000000000000000000000000000000000000000000000000000000005",
+        "000000000000000000000000000000000000000000000000000000006553f100",
+    );
+    for (transfer, selector) in [
+        (Transfer::RemoveLiquidity, "a849055a"),
+        (Transfer::Swap, "89d79c4c"),
+        (Transfer::Refund, "39792c94"),
+    ] {
+        assert_eq!(
+            hex(EVM.transfer(transfer, args.0, args.1, args.2, &envelope)?),
+            format!("{selector}{body}")
+        );
+    }
+
+    let r = EVM.transfer(Transfer::Refund, args.0, args.1, "0x1234", &envelope);
+    assert!(matches!(r, Err(ContractError::InvalidAddress { .. })));
+
+    Ok(())
+}
+
+/// Test the factory call encoding produces the same bytes as `ethabi`, which it replaced.
+#[test]
+fn abi_matches_ethabi() -> Result<(), ContractError> {
+    use ethabi::{Function, Param, ParamType, StateMutability, Token, Uint};
+    use std::str::FromStr;
+
+    #[allow(deprecated)]
+    let function = |name: &str, inputs: &[(&str, ParamType)]| Function {
+        name: name.to_string(),
+        inputs: inputs
+            .iter()
+            .cloned()
+            .chain([
+                ("nonce", ParamType::Uint(256)),
+                ("deadline", ParamType::Uint(256)),
+            ])
+            .map(|(name, kind)| Param {
+                name: name.to_string(),
+                kind,
+                internal_type: None,
+            })
+            .collect(),
+        outputs: Vec::new(),
+        constant: None,
+        state_mutability: StateMutability::NonPayable,
+    };
+    let transfer = [
+        ("pool_id", ParamType::Uint(256)),
+        ("amount", ParamType::Uint(256)),
+        ("recipient", ParamType::Address),
+    ];
+    let uint = |value: Uint256| Token::Uint(Uint::from_big_endian(&value.to_be_bytes()));
+
+    for i in 0..200u64 {
+        let pool_id = Uint256::from(i);
+        let amount = Uint256::MAX - Uint256::from(i * 7919);
+        let recipient = format!("0x{:040x}", i * 104729);
+        let envelope = Envelope {
+            nonce: i,
+            deadline: Timestamp::from_seconds(1_700_000_000 + i),
+        };
+        let tail = [
+            Token::Uint(Uint::from(i)),
+            Token::Uint(Uint::from(1_700_000_000 + i)),
+        ];
+
+        let encoded = [
+            EVM.create_pool(&recipient, pool_id, &envelope)?,
+            EVM.transfer(Transfer::Swap, pool_id, amount, &recipient, &envelope)?,
+            EVM.transfer(Transfer::Refund, pool_id, amount, &recipient, &envelope)?,
+            EVM.transfer(
+                Transfer::RemoveLiquidity,
+                pool_id,
+                amount,
+                &recipient,
+                &envelope,
+            )?,
+        ];
+
+        let address = Token::Address(ethabi::Address::from_str(&recipient).unwrap());
+        let create_pool = [address.clone(), uint(pool_id)];
+        let transfer_tokens = [uint(pool_id), uint(amount), address];
+        let expected = [
+            (
+                "create_pool",
+                &[
+                    ("token", ParamType::Address),
+                    ("pool_id", ParamType::Uint(256)),
+                ][..],
+                &create_pool[..],
+            ),
+            ("swap", &transfer[..], &transfer_tokens[..]),
+            ("refund", &transfer[..], &transfer_tokens[..]),
+            ("remove_liquidity", &transfer[..], &transfer_tokens[..]),
+        ]
+        .map(|(name, inputs, tokens)| {
+            let tokens: Vec<Token> = tokens.iter().chain(&tail).cloned().collect();
+            function(name, inputs).encode_input(&tokens).unwrap()
+        });
+
+        assert_eq!(encoded, expected);
+    }
+
+    Ok(())
+}
+
+/// Test encoding payloads as JSON for a CosmWasm chain and validating its tokens and accounts.
+#[test]
+fn cosmwasm_chain() -> Result<(), ContractError> {
+    let mut deps = mock_dependencies();
+    let info = mock_info("admin0000", &[]);
+    instantiate(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        InstantiateMsg { deadline: 1000 },
+    )?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        info.clone(),
+        ExecuteMsg::SetRelayer {
+            relayer: Some(Addr::unchecked("relayer")),
+        },
+    )?;
+    let (chain0_id, chain1_id): (Uint256, Uint256) = (42u32.into(), 99u32.into());
+    let account = "cosmos1qypqxpq9qcrsszg2pvxq6rs0zqg3yyc5lzv7xu";
+    for (chain_id, factory, encoding) in [
+        (chain0_id, "abcd", Encoding::Evm),
+        (chain1_id, "wxyz", Encoding::CosmWasm),
+    ] {
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::RegisterChain {
+                chain_id,
+                factory: factory.to_string(),
+                encoding: Some(encoding),
+                require_checksum: None,
+            },
+        )?;
+    }
+    list_token(deps.as_mut(), chain0_id, TOKEN0, 18, "WETH")?;
+    let r = list_token(deps.as_mut(), chain1_id, "0xabcd", 6, "ATOM");
+    assert!(matches!(r, Err(ContractError::InvalidToken { .. })));
+    list_token(deps.as_mut(), chain1_id, "uatom", 6, "ATOM")?;
+
+    let r = execute(
+        deps.as_mut(),
+        mock_env(),
+        info,
+        ExecuteMsg::CreatePool {
+            chain0_id,
+            chain1_id,
+            token0: TOKEN0.to_string(),
+            token1: "uatom".to_string(),
+            chain0_init_depositor: DEPOSITOR0.to_string(),
+            chain1_init_depositor: account.to_string(),
+            bootstrap_deadline: None,
+            matching_policy: None,
+            batch_window: None,
+        },
+    )?;
+    assert_eq!(r.messages.len(), 2);
+    let (job_id, payload) = match &r.messages[1].msg {
+        CosmosMsg::Custom(PalomaMsg { job_id, payload }) => (job_id, payload),
+        _ => panic!("expected a paloma message"),
+    };
+    assert_eq!(job_id, "wxyz");
+    assert_eq!(
+        from_slice::<FactoryExecuteMsg>(payload)?,
+        FactoryExecuteMsg::CreatePool {
+            token: "uatom".to_string(),
+            pool_id: Uint256::zero(),
+            nonce: 0,
+            deadline: mock_env().block.time.plus_seconds(3600),
+        }
+    );
+
+    let r = deposit(deps.as_mut(), chain1_id, "uatom", DEPOSITOR1, "alice", 1000);
+    assert!(matches!(r, Err(ContractError::InvalidAddress { .. })));
+    let mut checksum_broken = account.to_string();
+    checksum_broken.pop();
+    checksum_broken.push('q');
+    let r = deposit(
+        deps.as_mut(),
+        chain1_id,
+        "uatom",
+        &checksum_broken,
+        "alice",
+        1000,
+    );
+    assert!(matches!(r, Err(ContractError::InvalidAddress { .. })));
+    deposit(deps.as_mut(), chain1_id, "uatom", account, "alice", 1000)?;
+
+    Ok(())
+}
+
+/// Test EVM address validation, EIP-55 checksums and canonical storage.
+#[test]
+fn address_validation() -> Result<(), ContractError> {
+    let checksummed = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    let canonical = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+    assert_eq!(EVM.canonical_address(checksummed)?, canonical);
+    assert_eq!(EVM.canonical_address(&canonical[2..])?, canonical);
+    assert_eq!(
+        EVM.canonical_address(&canonical.to_uppercase().replace("0X", "0x"))?,
+        canonical
+    );
+    let r = EVM.canonical_address("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+    assert!(matches!(r, Err(ContractError::InvalidChecksum { .. })));
+    let r = EVM.canonical_address("0x0000000000000000000000000000000000000000");
+    assert!(matches!(r, Err(ContractError::ZeroAddress {})));
+    let r = EVM.canonical_token("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae");
+    assert!(matches!(r, Err(ContractError::InvalidToken { .. })));
+
+    let strict = Evm {
+        require_checksum: true,
+    };
+    assert_eq!(strict.canonical_address(checksummed)?, canonical);
+    let r = strict.canonical_address(canonical);
+    assert!(matches!(r, Err(ContractError::InvalidChecksum { .. })));
+
+    let mut deps = mock_dependencies();
+    let chains = setup(deps.as_mut())?;
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("admin0000", &[]),
+        create_pool_msg(chains, None),
+    )?;
+    // The same pool can't be created again through a differently cased token.
+    let mut msg = create_pool_msg(chains, None);
+    if let ExecuteMsg::CreatePool { token1, .. } = &mut msg {
+        *token1 = format!("0x{}", TOKEN1.to_uppercase());
+    }
+    let r = execute(deps.as_mut(), mock_env(), mock_info("admin0000", &[]), msg);
+    assert!(matches!(r, Err(ContractError::PoolExists { .. })));
+
+    let r = deposit(
+        deps.as_mut(),
+        chains.0,
+        TOKEN0,
+        "0x0000000000000000000000000000000000000000",
+        "alice",
+        1000,
+    );
+    assert!(matches!(r, Err(ContractError::ZeroAddress {})));
+    deposit(
+        deps.as_mut(),
+        chains.0,
+        TOKEN0,
+        &DEPOSITOR0[2..],
+        "alice",
+        1000,
+    )?;
+    let queue: LiquidityQueueResponse = from_binary(&query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::LiquidityQueue {
+            pool_id: 0u32.into(),
+            start_after: None,
+            limit: None,
+        },
+    )?)?;
+    assert_eq!(queue.deposits[0].deposit.sender, DEPOSITOR0);
 
     Ok(())
 }